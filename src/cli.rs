@@ -0,0 +1,214 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use structopt::StructOpt;
+
+use crate::{
+    config::{parse_config, ConfigError},
+    heatmap::{self, HeatmapRun},
+    highscore::HighScores,
+    history::{export_to, ExportFormat, History},
+    modes::GameMode,
+    paths::Paths,
+};
+
+// Flags override the config for this session only, nothing they change is saved
+#[derive(StructOpt, Debug)]
+#[structopt(name = "pocop", about = "An aim trainer")]
+pub struct Cli {
+    #[structopt(long, help = "Start in a window, whatever the config says")]
+    pub windowed: bool,
+    #[structopt(
+        long,
+        help = "Mode selected on the main menu: classic, time_attack, survival, zen or tracking"
+    )]
+    pub mode: Option<GameMode>,
+    #[structopt(long, help = "Seed for the target positions, the same seed gives the same targets")]
+    pub seed: Option<u64>,
+    #[structopt(long, help = "Config file to use instead of the one in the config directory")]
+    pub config: Option<PathBuf>,
+    #[structopt(long, help = "Directory for the config file")]
+    pub config_dir: Option<PathBuf>,
+    #[structopt(long, help = "Directory for high scores and other save data")]
+    pub data_dir: Option<PathBuf>,
+    #[structopt(
+        long,
+        parse(try_from_str = parse_duration),
+        help = "Length of timed runs in seconds"
+    )]
+    pub duration: Option<f32>,
+    #[structopt(long, help = "Watch a recorded run instead of playing")]
+    pub replay: Option<PathBuf>,
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+// A run that can't end, or ends before it starts, is refused along with the other bad arguments
+fn parse_duration(arg: &str) -> Result<f32, String> {
+    let seconds: f32 = arg.parse().map_err(|err| format!("{}", err))?;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err(format!("{} is not a positive number of seconds", arg));
+    }
+    Ok(seconds)
+}
+
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    #[structopt(about = "Print the high scores and exit")]
+    Scores {
+        #[structopt(long, help = "Only print this mode")]
+        mode: Option<GameMode>,
+    },
+    #[structopt(about = "Export every finished run for use in other tools and exit")]
+    Export {
+        #[structopt(long, default_value = "csv", help = "csv or json")]
+        format: ExportFormat,
+        #[structopt(long, help = "Only export this mode")]
+        mode: Option<GameMode>,
+        #[structopt(long, short, help = "File to write to instead of the standard output")]
+        output: Option<PathBuf>,
+    },
+    #[structopt(about = "Draw where the clicks of past runs landed as a PNG and exit")]
+    Heatmap {
+        #[structopt(help = "PNG file to write")]
+        output: PathBuf,
+        #[structopt(long, help = "Only use runs of this mode")]
+        mode: Option<GameMode>,
+        #[structopt(long, help = "Only use this many of the most recent runs")]
+        last: Option<usize>,
+    },
+    #[structopt(about = "Check a config file for errors and exit")]
+    CheckConfig {
+        #[structopt(help = "Defaults to the config the game would load")]
+        path: Option<PathBuf>,
+    },
+}
+
+// Runs a subcommand instead of the game, returns the exit code
+pub fn run_command(command: &Command, paths: &Paths) -> i32 {
+    match command {
+        Command::Scores { mode } => {
+            print_scores(&HighScores::load(paths), *mode);
+            0
+        }
+        Command::Export {
+            format,
+            mode,
+            output,
+        } => match export_to(paths, *mode, *format, output.as_deref()) {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("Could not export the history: {}", err);
+                1
+            }
+        },
+        Command::Heatmap { output, mode, last } => heatmap(paths, output, *mode, *last),
+        Command::CheckConfig { path } => {
+            check_config(path.as_deref().unwrap_or(&paths.config_file))
+        }
+    }
+}
+
+fn print_scores(high_scores: &HighScores, only: Option<GameMode>) {
+    let modes = GameMode::ALL
+        .iter()
+        .copied()
+        .filter(|mode| only.map_or(true, |only| only == *mode));
+
+    for mode in modes {
+        println!("{}", mode.name());
+
+        let mut empty = true;
+        for (place, run) in high_scores.top(mode).enumerate() {
+            println!(
+                "{:>3}. {:>6}  {:>3.0}% accuracy  {:.0}s",
+                place + 1,
+                run.score,
+                run.accuracy() * 100.0,
+                run.duration
+            );
+            empty = false;
+        }
+        if empty {
+            println!("     no runs yet");
+        }
+    }
+}
+
+fn heatmap(paths: &Paths, output: &Path, only: Option<GameMode>, last: Option<usize>) -> i32 {
+    let history = History::load(paths);
+    let mut runs: Vec<HeatmapRun> = history
+        .entries()
+        .iter()
+        .filter(|entry| only.map_or(true, |only| only == entry.run.mode))
+        .map(|entry| HeatmapRun {
+            clicks: &entry.clicks,
+        })
+        .collect();
+    if let Some(last) = last {
+        runs.drain(..runs.len().saturating_sub(last));
+    }
+
+    let clicks: usize = runs.iter().map(|run| run.clicks.len()).sum();
+    if clicks == 0 {
+        eprintln!("No clicks recorded yet");
+        return 1;
+    }
+
+    if let Err(err) = heatmap::save(&runs, output) {
+        eprintln!("Could not write {}: {}", output.display(), err);
+        return 1;
+    }
+
+    println!("{} clicks from {} runs written to {}", clicks, runs.len(), output.display());
+    if let Some(bias) = heatmap::bias(&runs) {
+        println!("{}", heatmap::describe_bias(bias));
+    }
+    0
+}
+
+fn check_config(path: &Path) -> i32 {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), ConfigError::Io(err));
+            return 1;
+        }
+    };
+
+    match parse_config(contents.as_str()) {
+        Ok((config, migrated)) => {
+            if migrated {
+                println!(
+                    "{} is valid, it will be upgraded to version {} on the next start",
+                    path.display(),
+                    config.version
+                );
+            } else {
+                println!("{} is valid", path.display());
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_must_be_a_positive_number() {
+        assert_eq!(parse_duration("30"), Ok(30.0));
+        assert_eq!(parse_duration("0.5"), Ok(0.5));
+
+        for arg in ["0", "-10", "inf", "NaN", "ten"].iter() {
+            assert!(parse_duration(arg).is_err(), "{} was accepted", arg);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use std::{fmt, io, path::Path};
+
+use bevy::{prelude::*, window::WindowMode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    difficulty::DifficultySettings,
+    hit_test::HitTestSettings,
+    modes::ModeSettings,
+    scoring::{default_rings, Ring},
+    spawner::SpawnPolicy,
+    storage::{self, LoadError},
+};
+
+// Bump this and add a step to `migrate` whenever the layout of the file changes
+pub const CONFIG_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    pub window: WindowSettings,
+    pub crosshair: CrosshairSettings,
+    // Multiplier for how far the crosshair moves with the mouse
+    pub sensitivity: f32,
+    pub difficulty: DifficultySettings,
+    pub audio: AudioSettings,
+    pub keybinds: Keybinds,
+    // Every run places its targets with this seed instead of a random one
+    pub seed: Option<u64>,
+    pub spawn: SpawnPolicy,
+    pub rings: Vec<Ring>,
+    pub modes: ModeSettings,
+    pub hit_test: HitTestSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            window: WindowSettings::default(),
+            crosshair: CrosshairSettings::default(),
+            sensitivity: 1.0,
+            difficulty: DifficultySettings::default(),
+            audio: AudioSettings::default(),
+            keybinds: Keybinds::default(),
+            seed: None,
+            spawn: SpawnPolicy::default(),
+            rings: default_rings(),
+            modes: ModeSettings::default(),
+            hit_test: HitTestSettings::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowModeSetting {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub const ALL: [WindowModeSetting; 3] = [
+        WindowModeSetting::Windowed,
+        WindowModeSetting::Borderless,
+        WindowModeSetting::Fullscreen,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowModeSetting::Windowed => "Windowed",
+            WindowModeSetting::Borderless => "Borderless",
+            WindowModeSetting::Fullscreen => "Fullscreen",
+        }
+    }
+}
+
+impl From<WindowModeSetting> for WindowMode {
+    fn from(mode: WindowModeSetting) -> Self {
+        match mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen { use_size: true },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub mode: WindowModeSetting,
+    // Size of the window in windowed mode and the resolution in fullscreen mode
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            mode: WindowModeSetting::Borderless,
+            width: 1280.0,
+            height: 720.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CrosshairStyle {
+    // The crosshair image
+    Image,
+    // A small solid square
+    Dot,
+}
+
+impl CrosshairStyle {
+    pub const ALL: [CrosshairStyle; 2] = [CrosshairStyle::Image, CrosshairStyle::Dot];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrosshairStyle::Image => "Image",
+            CrosshairStyle::Dot => "Dot",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CrosshairSettings {
+    pub style: CrosshairStyle,
+    pub size: f32,
+    // Red, green and blue from 0.0 to 1.0
+    pub color: [f32; 3],
+}
+
+impl Default for CrosshairSettings {
+    fn default() -> Self {
+        CrosshairSettings {
+            style: CrosshairStyle::Image,
+            size: 60.0,
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl CrosshairSettings {
+    pub fn color(&self) -> Color {
+        Color::rgb(self.color[0], self.color[1], self.color[2])
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AudioSettings {
+    // From 0.0 to 1.0
+    pub volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings { volume: 0.8 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Keybinds {
+    pub pause: KeyCode,
+    // Ends a zen run
+    pub finish_run: KeyCode,
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Keybinds {
+            pause: KeyCode::Escape,
+            finish_run: KeyCode::Return,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    // The file was written by a newer version of the game
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "could not read the config: {}", err),
+            ConfigError::Parse(err) => write!(f, "the config is not valid: {}", err),
+            ConfigError::UnsupportedVersion(version) => write!(
+                f,
+                "the config has version {} but this version of pocop only knows up to {}",
+                version, CONFIG_VERSION
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(err: serde_json::Error) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+// Parses a config file, upgrading it from older versions. Missing fields get their defaults.
+// The second value tells if the file was migrated and should be written back.
+pub fn parse_config(contents: &str) -> Result<(Config, bool), ConfigError> {
+    let mut value: Value = serde_json::from_str(contents)?;
+
+    // Files from before the version field was added are version 0
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > CONFIG_VERSION {
+        return Err(ConfigError::UnsupportedVersion(version));
+    }
+
+    for from in version..CONFIG_VERSION {
+        migrate(&mut value, from);
+    }
+
+    let config = serde_json::from_value(value)?;
+
+    Ok((config, version != CONFIG_VERSION))
+}
+
+// Upgrades the raw file from version `from` to `from + 1`
+fn migrate(value: &mut Value, from: u32) {
+    if let (0, Some(object)) = (from, value.as_object_mut()) {
+        // Version 0 only had a fullscreen checkbox
+        let fullscreen = object
+            .remove("fullscreen")
+            .and_then(|fullscreen| fullscreen.as_bool())
+            .unwrap_or(true);
+        let mode = if fullscreen {
+            WindowModeSetting::Borderless
+        } else {
+            WindowModeSetting::Windowed
+        };
+
+        object.insert("window".to_string(), json!({ "mode": mode }));
+    }
+
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), json!(from + 1));
+    }
+}
+
+pub fn load_config(path: &Path) -> Result<(Config, bool), ConfigError> {
+    // A file from a newer version isn't broken, so it must not be replaced with the backup
+    let loaded = storage::load(path, |contents| {
+        match parse_config(contents) {
+            Err(ConfigError::UnsupportedVersion(version)) => Ok(Err(version)),
+            result => result.map(Ok),
+        }
+    });
+
+    match loaded {
+        Ok(Ok(config)) => Ok(config),
+        Ok(Err(version)) => Err(ConfigError::UnsupportedVersion(version)),
+        Err(LoadError::Missing) => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+        Err(LoadError::Io(err)) => Err(err.into()),
+        Err(LoadError::Invalid(err)) => Err(err),
+    }
+}
+
+// Loads the config. A missing or broken file without a usable backup is replaced with the
+// defaults, a broken file is kept next to it so it can be fixed by hand. A file that can't be
+// read is left alone.
+pub fn get_config(path: &Path) -> Config {
+    match load_config(path) {
+        Ok((config, migrated)) => {
+            if migrated {
+                save_config(&config, path);
+            }
+            config
+        }
+        Err(ConfigError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+            let config = Config::default();
+            save_config(&config, path);
+            config
+        }
+        Err(ConfigError::UnsupportedVersion(version)) => {
+            // Leave the newer file alone and play with the defaults
+            eprintln!(
+                "{}: {}",
+                path.display(),
+                ConfigError::UnsupportedVersion(version)
+            );
+            Config::default()
+        }
+        Err(ConfigError::Io(err)) => {
+            // The file may be fine but unreadable for now, like when permissions are wrong, so it
+            // is neither moved nor overwritten
+            eprintln!(
+                "{}: {}, using the defaults",
+                path.display(),
+                ConfigError::Io(err)
+            );
+            Config::default()
+        }
+        Err(err) => {
+            match storage::set_aside(path) {
+                Ok(broken) => eprintln!(
+                    "{}: {}, moved it to {} and using the defaults",
+                    path.display(),
+                    err,
+                    broken.display()
+                ),
+                Err(move_err) => eprintln!(
+                    "{}: {}, using the defaults. Could not move it: {}",
+                    path.display(),
+                    err,
+                    move_err
+                ),
+            }
+
+            let config = Config::default();
+            save_config(&config, path);
+            config
+        }
+    }
+}
+
+pub fn save_config(config: &Config, path: &Path) {
+    let j = serde_json::to_string_pretty(config).unwrap();
+
+    if let Err(err) = storage::write_atomic(path, &j) {
+        eprintln!("Could not write {}: {}", path.display(), err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, process};
+
+    use super::*;
+
+    // Empty directory of its own for every test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pocop-config-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn version_0_fullscreen_becomes_a_window_mode() {
+        let (config, migrated) =
+            parse_config(r#"{ "fullscreen": false, "sensitivity": 2.0 }"#).unwrap();
+        assert!(migrated);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.window.mode, WindowModeSetting::Windowed);
+        assert_eq!(config.sensitivity, 2.0);
+
+        let (config, _) = parse_config(r#"{ "fullscreen": true }"#).unwrap();
+        assert_eq!(config.window.mode, WindowModeSetting::Borderless);
+
+        // Files from before the checkbox was added played fullscreen
+        let (config, _) = parse_config("{}").unwrap();
+        assert_eq!(config.window.mode, WindowModeSetting::Borderless);
+    }
+
+    #[test]
+    fn missing_fields_get_their_defaults() {
+        let (config, migrated) =
+            parse_config(r#"{ "version": 1, "crosshair": { "size": 30.0 } }"#).unwrap();
+        let defaults = Config::default();
+
+        assert!(!migrated);
+        assert_eq!(config.crosshair.size, 30.0);
+        assert_eq!(config.crosshair.style, defaults.crosshair.style);
+        assert_eq!(config.crosshair.color, defaults.crosshair.color);
+        assert_eq!(config.sensitivity, defaults.sensitivity);
+        assert_eq!(config.window.width, defaults.window.width);
+        assert_eq!(config.keybinds.pause, defaults.keybinds.pause);
+        assert_eq!(config.rings.len(), defaults.rings.len());
+    }
+
+    #[test]
+    fn corrupt_file_is_set_aside_and_replaced_with_the_defaults() {
+        let dir = test_dir("corrupt");
+        let path = dir.join("config.json");
+        fs::write(&path, "{ \"sensitivity\": ").unwrap();
+
+        let config = get_config(&path);
+        let broken = fs::read_to_string(dir.join("config.json.broken"));
+        let written = fs::read_to_string(&path).map(|contents| parse_config(&contents).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(config.sensitivity, Config::default().sensitivity);
+        assert_eq!(broken.unwrap(), "{ \"sensitivity\": ");
+        assert!(written.unwrap());
+    }
+
+    #[test]
+    fn unreadable_file_is_left_alone() {
+        let dir = test_dir("unreadable");
+        // Reading a directory fails with an error other than a missing file
+        let path = dir.join("config.json");
+        fs::create_dir(&path).unwrap();
+
+        let config = get_config(&path);
+        let kept = path.is_dir();
+        let set_aside = dir.join("config.json.broken").exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(config.sensitivity, Config::default().sensitivity);
+        assert!(kept);
+        assert!(!set_aside);
+    }
+}
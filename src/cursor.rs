@@ -0,0 +1,65 @@
+use bevy::{prelude::*, render::camera::Camera};
+
+use crate::{ingame::Aim, GameState};
+
+// Marks the camera the targets are drawn with, the UI has a camera of its own
+pub struct MainCamera;
+
+// Where the pointer is in world coordinates this frame, None when it is outside the window.
+// While playing the pointer is the crosshair, elsewhere it is the mouse cursor. Replays set it
+// themselves from the recording.
+#[derive(Default)]
+pub struct CursorWorld(pub Option<Vec2>);
+
+pub struct CursorPlugin;
+
+impl Plugin for CursorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(CursorWorld::default()).add_system(
+            update_cursor_world
+                .system()
+                .label("cursor_world")
+                .after("input"),
+        );
+    }
+}
+
+// Undoes the camera's projection and transform, `screen` is in logical pixels from the bottom
+// left corner of the window like `Window::cursor_position`
+pub fn screen_to_world(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    screen: Vec2,
+) -> Vec2 {
+    let window_size = Vec2::new(window.width(), window.height());
+    let ndc = screen / window_size * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+
+    ndc_to_world.project_point3(ndc.extend(0.0)).truncate()
+}
+
+fn update_cursor_world(
+    windows: Res<Windows>,
+    game_state: Res<State<GameState>>,
+    aim: Res<Aim>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut cursor_world: ResMut<CursorWorld>,
+) {
+    let (camera, camera_transform) = match camera_query.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let window = match windows.get(camera.window) {
+        Some(window) => window,
+        None => return,
+    };
+
+    let screen = match game_state.current() {
+        GameState::InGame => Some(aim.0),
+        GameState::Replay => return,
+        _ => window.cursor_position(),
+    };
+
+    cursor_world.0 = screen.map(|screen| screen_to_world(window, camera, camera_transform, screen));
+}
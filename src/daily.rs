@@ -0,0 +1,280 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    difficulty::{Difficulty, DifficultyProfile},
+    highscore::{now_timestamp, RunEndEvent, RunResult},
+    hit_test::{HitTest, HitTestSettings},
+    modes::{GameMode, ModeSettings},
+    paths::Paths,
+    rng::FixedSeed,
+    scoring::{default_rings, ScoringRings},
+    spawner::{SpawnPolicy, SpawnScheduler},
+    storage, GameState,
+};
+
+const DAILY_FILE: &str = "daily.json";
+// Everyone plays the daily challenge with the same rules, whatever their own settings are
+const DAILY_MODE: GameMode = GameMode::TimeAttack;
+const DAILY_TIME_LIMIT: f32 = 60.0;
+
+// Marks the daily challenge button on the main menu
+pub struct DailyButton;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DailyEntry {
+    // Days since the unix epoch
+    pub day: u64,
+    // None when the attempt was started but never finished
+    pub run: Option<RunResult>,
+}
+
+// The scored attempt of every day, kept apart from the high scores
+#[derive(Serialize, Deserialize, Default)]
+pub struct DailyResults {
+    entries: Vec<DailyEntry>,
+}
+
+impl DailyResults {
+    pub fn load(paths: &Paths) -> Self {
+        storage::load_json(&paths.data_file(DAILY_FILE))
+    }
+
+    pub fn save(&self, paths: &Paths) {
+        storage::save_json(&paths.data_file(DAILY_FILE), self);
+    }
+
+    pub fn get(&self, day: u64) -> Option<&DailyEntry> {
+        self.entries.iter().find(|entry| entry.day == day)
+    }
+
+    fn get_mut(&mut self, day: u64) -> Option<&mut DailyEntry> {
+        self.entries.iter_mut().find(|entry| entry.day == day)
+    }
+}
+
+// Settings the daily challenge replaced, put back when going back to the main menu
+struct Stash {
+    mode: GameMode,
+    mode_settings: ModeSettings,
+    fixed_seed: Option<u64>,
+    rings: ScoringRings,
+    hit_test: HitTestSettings,
+}
+
+struct ActiveDaily {
+    day: u64,
+    // Only the first attempt of the day counts, later ones are practice
+    scored: bool,
+    stash: Stash,
+}
+
+// Set while the daily challenge is being played
+#[derive(Default)]
+pub struct DailyRun(Option<ActiveDaily>);
+
+impl DailyRun {
+    pub fn is_active(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn is_scored(&self) -> bool {
+        self.0.as_ref().map_or(false, |daily| daily.scored)
+    }
+
+    pub fn day(&self) -> Option<u64> {
+        self.0.as_ref().map(|daily| daily.day)
+    }
+}
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let results = DailyResults::load(app.world().get_resource::<Paths>().unwrap());
+
+        app.insert_resource(results)
+            .insert_resource(DailyRun::default())
+            .add_system(record_daily.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::MainMenu).with_system(start_daily.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainMenu).with_system(end_daily.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame).with_system(begin_attempt.system()),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::InGame).with_system(forfeit_attempt.system()),
+            );
+    }
+}
+
+pub fn today() -> u64 {
+    now_timestamp() / 86400
+}
+
+// Year, month and day of a day since the unix epoch, in UTC
+fn civil_date(day: u64) -> (i64, u32, u32) {
+    // Howard Hinnant's civil_from_days algorithm
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+
+    (y, m, d)
+}
+
+pub fn date_string(day: u64) -> String {
+    let (y, m, d) = civil_date(day);
+
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+// The date written as a number, 2021-07-14 plays with seed 20210714
+pub fn date_seed(day: u64) -> u64 {
+    let (y, m, d) = civil_date(day);
+
+    y as u64 * 10000 + m as u64 * 100 + d as u64
+}
+
+pub fn button_label(results: &DailyResults) -> String {
+    match results.get(today()) {
+        Some(DailyEntry { run: Some(run), .. }) => format!("Daily: {} (practice)", run.score),
+        Some(DailyEntry { run: None, .. }) => "Daily (practice)".to_string(),
+        None => "Daily challenge".to_string(),
+    }
+}
+
+fn start_daily(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<DailyButton>)>,
+    mut game_state: ResMut<State<GameState>>,
+    mut daily: ResMut<DailyRun>,
+    mut mode: ResMut<GameMode>,
+    mut mode_settings: ResMut<ModeSettings>,
+    mut fixed_seed: ResMut<FixedSeed>,
+    mut difficulty: ResMut<Difficulty>,
+    mut scheduler: ResMut<SpawnScheduler>,
+    mut rings: ResMut<ScoringRings>,
+    mut hit_test: ResMut<HitTest>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Clicked || daily.is_active() {
+            continue;
+        }
+        // Another screen change is already on its way, the player's settings stay as they are
+        if game_state.set(GameState::InGame).is_err() {
+            continue;
+        }
+
+        let day = today();
+        let profile = DifficultyProfile::normal();
+
+        daily.0 = Some(ActiveDaily {
+            day,
+            scored: false,
+            stash: Stash {
+                mode: *mode,
+                mode_settings: mode_settings.clone(),
+                fixed_seed: fixed_seed.0,
+                rings: rings.clone(),
+                hit_test: hit_test.settings().clone(),
+            },
+        });
+
+        *mode = DAILY_MODE;
+        mode_settings.time_limit = DAILY_TIME_LIMIT;
+        fixed_seed.0 = Some(date_seed(day));
+        *scheduler = SpawnScheduler::new(SpawnPolicy::default(), profile.spawn_rate);
+        difficulty.0 = profile;
+        *rings = ScoringRings::new(default_rings());
+        hit_test.set_settings(HitTestSettings::default());
+    }
+}
+
+// Puts the player's own settings back
+fn end_daily(
+    mut daily: ResMut<DailyRun>,
+    config: Res<Config>,
+    mut mode: ResMut<GameMode>,
+    mut mode_settings: ResMut<ModeSettings>,
+    mut fixed_seed: ResMut<FixedSeed>,
+    mut difficulty: ResMut<Difficulty>,
+    mut scheduler: ResMut<SpawnScheduler>,
+    mut rings: ResMut<ScoringRings>,
+    mut hit_test: ResMut<HitTest>,
+) {
+    if let Some(active) = daily.0.take() {
+        let stash = active.stash;
+
+        *mode = stash.mode;
+        *mode_settings = stash.mode_settings;
+        fixed_seed.0 = stash.fixed_seed;
+        *rings = stash.rings;
+        hit_test.set_settings(stash.hit_test);
+        // Taken from the config in case it was changed on the settings screen meanwhile
+        difficulty.0 = config.difficulty.profile();
+        *scheduler = SpawnScheduler::new(config.spawn.clone(), difficulty.0.spawn_rate);
+    }
+}
+
+// Runs at the start of every daily run, including retries
+fn begin_attempt(
+    mut daily: ResMut<DailyRun>,
+    mut results: ResMut<DailyResults>,
+    paths: Res<Paths>,
+) {
+    if let Some(active) = &mut daily.0 {
+        active.scored = results.get(active.day).is_none();
+
+        // The attempt counts as soon as it starts, so quitting halfway doesn't give another one
+        if active.scored {
+            results.entries.push(DailyEntry {
+                day: active.day,
+                run: None,
+            });
+            results.save(&paths);
+        }
+    }
+}
+
+// Pausing restarts the run, which would give a second look at the same targets
+fn forfeit_attempt(mut daily: ResMut<DailyRun>) {
+    if let Some(active) = &mut daily.0 {
+        active.scored = false;
+    }
+}
+
+fn record_daily(
+    mut ev_run_end: EventReader<RunEndEvent>,
+    daily: Res<DailyRun>,
+    mut results: ResMut<DailyResults>,
+    paths: Res<Paths>,
+) {
+    for run in ev_run_end.iter() {
+        if !run.0.daily {
+            continue;
+        }
+
+        if let Some(active) = &daily.0 {
+            if !active.scored {
+                continue;
+            }
+
+            if let Some(entry) = results.get_mut(active.day) {
+                if entry.run.is_none() {
+                    println!("Daily challenge {}: {}", date_string(active.day), run.0.score);
+                    entry.run = Some(run.0.clone());
+                    results.save(&paths);
+                }
+            }
+        }
+    }
+}
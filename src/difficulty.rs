@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+// Limits for `target_scale`, bigger targets would not fit on the playfield
+const MIN_TARGET_SCALE: f32 = 0.1;
+const MAX_TARGET_SCALE: f32 = 5.0;
+
+// Fields missing from a custom profile come from the normal one
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DifficultyProfile {
+    // Gravity at the start of a run
+    pub start_gravity: f32,
+    // Gravity added for every hit
+    pub gravity_step: f32,
+    // Gravity never goes above this
+    pub gravity_cap: f32,
+    // Multiplier for the size of the targets
+    pub target_scale: f32,
+    // Multiplier for how often timed and burst spawns happen. Keep alive spawns don't run on a
+    // timer, so the easy and hard profiles leave it at 1.0 and only a custom profile changes it.
+    pub spawn_rate: f32,
+}
+
+impl DifficultyProfile {
+    pub fn easy() -> Self {
+        DifficultyProfile {
+            start_gravity: 0.6,
+            gravity_step: 0.05,
+            gravity_cap: 3.0,
+            target_scale: 1.3,
+            spawn_rate: 1.0,
+        }
+    }
+
+    pub fn normal() -> Self {
+        DifficultyProfile {
+            start_gravity: 1.0,
+            gravity_step: 0.1,
+            gravity_cap: 6.0,
+            target_scale: 1.0,
+            spawn_rate: 1.0,
+        }
+    }
+
+    pub fn hard() -> Self {
+        DifficultyProfile {
+            start_gravity: 1.5,
+            gravity_step: 0.15,
+            gravity_cap: 10.0,
+            target_scale: 0.75,
+            spawn_rate: 1.0,
+        }
+    }
+
+    // Keeps values from the config or a replay in a range targets can be spawned with
+    pub fn clamped(mut self) -> Self {
+        self.target_scale = self.target_scale.max(MIN_TARGET_SCALE).min(MAX_TARGET_SCALE);
+        self
+    }
+
+    pub fn next_gravity(&self, gravity: f32) -> f32 {
+        (gravity + self.gravity_step).min(self.gravity_cap)
+    }
+}
+
+impl Default for DifficultyProfile {
+    fn default() -> Self {
+        DifficultyProfile::normal()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DifficultyLevel {
+    Easy,
+    Normal,
+    Hard,
+    // Uses the custom profile from the config
+    Custom,
+}
+
+impl DifficultyLevel {
+    pub const ALL: [DifficultyLevel; 4] = [
+        DifficultyLevel::Easy,
+        DifficultyLevel::Normal,
+        DifficultyLevel::Hard,
+        DifficultyLevel::Custom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DifficultyLevel::Easy => "Easy",
+            DifficultyLevel::Normal => "Normal",
+            DifficultyLevel::Hard => "Hard",
+            DifficultyLevel::Custom => "Custom",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DifficultySettings {
+    pub level: DifficultyLevel,
+    pub custom: DifficultyProfile,
+}
+
+impl Default for DifficultySettings {
+    fn default() -> Self {
+        DifficultySettings {
+            level: DifficultyLevel::Normal,
+            custom: DifficultyProfile::default(),
+        }
+    }
+}
+
+impl DifficultySettings {
+    pub fn profile(&self) -> DifficultyProfile {
+        match self.level {
+            DifficultyLevel::Easy => DifficultyProfile::easy(),
+            DifficultyLevel::Normal => DifficultyProfile::normal(),
+            DifficultyLevel::Hard => DifficultyProfile::hard(),
+            DifficultyLevel::Custom => self.custom.clone().clamped(),
+        }
+    }
+}
+
+// The profile used by the current session
+pub struct Difficulty(pub DifficultyProfile);
@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+
+use crate::{
+    daily::{date_string, DailyResults, DailyRun},
+    heatmap::{self, HeatmapRun},
+    highscore::{HighScores, LastRun},
+    paths::Paths,
+    replay::{LastReplay, QueuedReplay},
+    ui::{spawn_label, spawn_text_button},
+    GameState, Materials,
+};
+
+const HEATMAP_DIR: &str = "heatmaps";
+
+struct GameOverScreenRelated;
+
+enum GameOverButton {
+    Retry,
+    WatchReplay,
+    SaveHeatmap,
+    MainMenu,
+}
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::GameOver).with_system(game_over_setup.system()),
+        )
+        .add_system_set(
+            SystemSet::on_resume(GameState::GameOver).with_system(game_over_setup.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::GameOver).with_system(game_over_buttons.system()),
+        )
+        .add_system_set(
+            SystemSet::on_pause(GameState::GameOver).with_system(game_over_exit.system()),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::GameOver).with_system(game_over_exit.system()),
+        );
+    }
+}
+
+fn game_over_setup(
+    mut commands: Commands,
+    mut color_material: ResMut<Assets<ColorMaterial>>,
+    materials: Res<Materials>,
+    last_run: Res<LastRun>,
+    high_scores: Res<HighScores>,
+    daily: Res<DailyRun>,
+    daily_results: Res<DailyResults>,
+    last_replay: Res<LastReplay>,
+) {
+    let run = match &last_run.run {
+        Some(run) => run,
+        None => return,
+    };
+    let best = high_scores
+        .best(run.mode)
+        .map_or(run.score, |best| best.score.max(run.score));
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+
+                ..Default::default()
+            },
+            material: color_material.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(GameOverScreenRelated)
+        .with_children(|parent| {
+            spawn_label(parent, &materials, "Game over", 100.0);
+            if let (true, Some(day)) = (run.daily, daily.day()) {
+                let title = format!("Daily challenge {}", date_string(day));
+                spawn_label(parent, &materials, &title, 50.0);
+                spawn_label(parent, &materials, &format!("Score {}", run.score), 50.0);
+
+                let today = daily_results
+                    .get(day)
+                    .and_then(|entry| entry.run.as_ref())
+                    .map(|run| run.score);
+                let attempt = match (daily.is_scored(), today) {
+                    (true, _) => "Today's attempt".to_string(),
+                    (false, Some(score)) => format!("Practice, today's score {}", score),
+                    (false, None) => "Practice, today's attempt was not finished".to_string(),
+                };
+                spawn_label(parent, &materials, &attempt, 50.0);
+            } else {
+                spawn_label(parent, &materials, run.mode.name(), 50.0);
+                spawn_label(parent, &materials, &format!("Score {}", run.score), 50.0);
+                spawn_label(parent, &materials, &format!("Best {}", best), 50.0);
+            }
+            spawn_label(
+                parent,
+                &materials,
+                &format!(
+                    "Accuracy {:.0}% ({} of {})",
+                    run.accuracy() * 100.0,
+                    run.hits,
+                    run.hits + run.misses
+                ),
+                50.0,
+            );
+            if let Some(tracking) = run.tracking {
+                let value = format!("On target {:.0}%", tracking * 100.0);
+                spawn_label(parent, &materials, &value, 50.0);
+            }
+            if let Some(precision) = run.precision {
+                let value = format!("Precision {:.0}%", precision * 100.0);
+                spawn_label(parent, &materials, &value, 50.0);
+            }
+            if let (Some(mean), Some(median), Some(best)) =
+                (run.reaction_mean, run.reaction_median, run.reaction_best)
+            {
+                spawn_label(
+                    parent,
+                    &materials,
+                    &format!(
+                        "Reaction {:.0} ms mean, {:.0} ms median, {:.0} ms best",
+                        mean * 1000.0,
+                        median * 1000.0,
+                        best * 1000.0
+                    ),
+                    30.0,
+                );
+            }
+
+            if let Some(seed) = run.seed {
+                spawn_label(parent, &materials, &format!("Seed {}", seed), 30.0);
+            }
+
+            spawn_text_button(parent, &materials, "Retry").insert(GameOverButton::Retry);
+            if last_replay.0.is_some() {
+                spawn_text_button(parent, &materials, "Watch replay")
+                    .insert(GameOverButton::WatchReplay);
+            }
+            if !last_run.clicks.is_empty() {
+                spawn_text_button(parent, &materials, "Save heatmap")
+                    .insert(GameOverButton::SaveHeatmap);
+            }
+            spawn_text_button(parent, &materials, "Main menu").insert(GameOverButton::MainMenu);
+        });
+}
+
+fn game_over_exit(
+    mut commands: Commands,
+    query: Query<Entity, With<GameOverScreenRelated>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn game_over_buttons(
+    mut game_state: ResMut<State<GameState>>,
+    query: Query<(&Interaction, &GameOverButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+    last_replay: Res<LastReplay>,
+    mut queued: ResMut<QueuedReplay>,
+    last_run: Res<LastRun>,
+    paths: Res<Paths>,
+) {
+    for (interaction, button, children) in query.iter() {
+        if let Interaction::Clicked = *interaction {
+            match button {
+                GameOverButton::Retry => {
+                    let _ = game_state.set(GameState::InGame);
+                }
+                // Started by the replay plugin, which comes back here when it ends
+                GameOverButton::WatchReplay => queued.0 = last_replay.0.clone(),
+                GameOverButton::SaveHeatmap => {
+                    let label = save_heatmap(&last_run, &paths);
+                    for child in children.iter() {
+                        if let Ok(mut text) = text_query.get_mut(*child) {
+                            text.sections[0].value = label.to_string();
+                        }
+                    }
+                }
+                GameOverButton::MainMenu => {
+                    let _ = game_state.set(GameState::MainMenu);
+                }
+            }
+        }
+    }
+}
+
+// Saves the clicks of the run that just ended to the heatmaps data directory, returns the new
+// button label
+fn save_heatmap(last_run: &LastRun, paths: &Paths) -> &'static str {
+    let run = match &last_run.run {
+        Some(run) => run,
+        None => return "No run to save",
+    };
+    let runs = [HeatmapRun {
+        clicks: &last_run.clicks,
+    }];
+
+    let name = format!("{}-{}.png", run.date, run.mode.key());
+    let path = paths.data_file(HEATMAP_DIR).join(name);
+
+    match heatmap::save(&runs, &path) {
+        Ok(()) => {
+            println!("Heatmap saved to {}", path.display());
+            if let Some(bias) = heatmap::bias(&runs) {
+                println!("{}", heatmap::describe_bias(bias));
+            }
+            "Heatmap saved"
+        }
+        Err(err) => {
+            eprintln!("Could not write {}: {}", path.display(), err);
+            "Could not save the heatmap"
+        }
+    }
+}
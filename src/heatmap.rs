@@ -0,0 +1,259 @@
+use std::{fs, path::Path};
+
+use image::{ImageResult, Rgb, RgbImage};
+
+use crate::{
+    playfield::{PLAYFIELD_HEIGHT, PLAYFIELD_WIDTH},
+    stats::Click,
+};
+
+const PANEL_HEIGHT: u32 = 400;
+// The screen panel has the shape of the playfield
+const SCREEN_PANEL_WIDTH: u32 = PANEL_HEIGHT * 16 / 9;
+const GAP: u32 = 10;
+// The target panel shows clicks up to this many target radii from the center
+const TARGET_RANGE: f32 = 2.5;
+// Standard deviation in pixels of the spot every click adds
+const SPREAD: f32 = 6.0;
+const BACKGROUND: [f32; 3] = [0.927, 0.927, 0.927];
+const GUIDE: [f32; 3] = [0.6, 0.6, 0.6];
+// Colors from few clicks to many
+const RAMP: [[f32; 3]; 3] = [[0.2, 0.45, 0.8], [0.85, 0.2, 0.2], [1.0, 0.85, 0.2]];
+
+// The clicks of one run
+pub struct HeatmapRun<'a> {
+    pub clicks: &'a [Click],
+}
+
+// Offsets from the target centers turned so the crosshair always moved left to right before the
+// click, in target radii. A positive x is an overshoot, a positive y is to the left of the
+// movement. Clicks made without moving keep their offset as it is.
+fn aligned_offsets<'a>(runs: &'a [HeatmapRun]) -> impl Iterator<Item = [f32; 2]> + 'a {
+    runs.iter().flat_map(|run| {
+        // Every run starts with the crosshair in the middle
+        let starts = std::iter::once([0.0, 0.0])
+            .chain(run.clicks.iter().map(|click| click.position));
+
+        run.clicks.iter().zip(starts).filter_map(|(click, start)| {
+            let offset = click.offset?;
+            let movement = [click.position[0] - start[0], click.position[1] - start[1]];
+            let length = (movement[0] * movement[0] + movement[1] * movement[1]).sqrt();
+
+            if length < 1.0 {
+                return Some(offset);
+            }
+
+            let along = [movement[0] / length, movement[1] / length];
+            Some([
+                offset[0] * along[0] + offset[1] * along[1],
+                offset[1] * along[0] - offset[0] * along[1],
+            ])
+        })
+    })
+}
+
+// Mean aligned offset in target radii, see `aligned_offsets`
+pub fn bias(runs: &[HeatmapRun]) -> Option<[f32; 2]> {
+    let mut sum = [0.0, 0.0];
+    let mut count = 0;
+
+    for offset in aligned_offsets(runs) {
+        sum[0] += offset[0];
+        sum[1] += offset[1];
+        count += 1;
+    }
+
+    if count == 0 {
+        None
+    } else {
+        Some([sum[0] / count as f32, sum[1] / count as f32])
+    }
+}
+
+pub fn describe_bias(bias: [f32; 2]) -> String {
+    let along = if bias[0] >= 0.0 { "overshoot" } else { "undershoot" };
+    let across = if bias[1] >= 0.0 { "left" } else { "right" };
+
+    format!(
+        "On average you {} by {:.2} target radii and land {:.2} radii to the {} of your movement",
+        along,
+        bias[0].abs(),
+        bias[1].abs(),
+        across
+    )
+}
+
+// Clicks relative to the target on the left and to the playfield on the right
+fn render(runs: &[HeatmapRun]) -> RgbImage {
+    let mut target = Density::new(PANEL_HEIGHT, PANEL_HEIGHT);
+    let target_scale = PANEL_HEIGHT as f32 / 2.0 / TARGET_RANGE;
+    for offset in aligned_offsets(runs) {
+        target.splat(
+            PANEL_HEIGHT as f32 / 2.0 + offset[0] * target_scale,
+            PANEL_HEIGHT as f32 / 2.0 - offset[1] * target_scale,
+        );
+    }
+
+    let mut screen = Density::new(SCREEN_PANEL_WIDTH, PANEL_HEIGHT);
+    for run in runs.iter() {
+        for click in run.clicks.iter() {
+            // -1.0 to 1.0 from the center of the playfield
+            let x = click.position[0] / (PLAYFIELD_WIDTH / 2.0);
+            let y = click.position[1] / (PLAYFIELD_HEIGHT / 2.0);
+
+            screen.splat(
+                (x + 1.0) / 2.0 * SCREEN_PANEL_WIDTH as f32,
+                (1.0 - y) / 2.0 * PANEL_HEIGHT as f32,
+            );
+        }
+    }
+
+    let mut image = RgbImage::new(PANEL_HEIGHT + GAP + SCREEN_PANEL_WIDTH, PANEL_HEIGHT);
+    for pixel in image.pixels_mut() {
+        *pixel = rgb(BACKGROUND);
+    }
+
+    target.draw(&mut image, 0);
+    screen.draw(&mut image, PANEL_HEIGHT + GAP);
+
+    // The edge of the target and its center
+    let center = PANEL_HEIGHT as f32 / 2.0;
+    draw_circle(&mut image, center, center, target_scale);
+    draw_cross(&mut image, center, center, 10.0);
+    if let Some(bias) = bias(runs) {
+        let x = center + bias[0] * target_scale;
+        let y = center - bias[1] * target_scale;
+        draw_cross(&mut image, x, y, 6.0);
+    }
+
+    // The edge of the playfield and its center
+    let left = (PANEL_HEIGHT + GAP) as f32;
+    draw_rect(&mut image, left, SCREEN_PANEL_WIDTH as f32);
+    draw_cross(
+        &mut image,
+        left + SCREEN_PANEL_WIDTH as f32 / 2.0,
+        PANEL_HEIGHT as f32 / 2.0,
+        10.0,
+    );
+
+    image
+}
+
+pub fn save(runs: &[HeatmapRun], path: &Path) -> ImageResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    render(runs).save(path)
+}
+
+struct Density {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl Density {
+    fn new(width: u32, height: u32) -> Self {
+        Density {
+            width,
+            height,
+            values: vec![0.0; (width * height) as usize],
+        }
+    }
+
+    // Adds a gaussian spot around (x, y), clicks outside the panel are left out
+    fn splat(&mut self, x: f32, y: f32) {
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return;
+        }
+
+        let reach = (SPREAD * 3.0).ceil() as i64;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let px = x as i64 + dx;
+                let py = y as i64 + dy;
+                if px < 0 || py < 0 || px >= self.width as i64 || py >= self.height as i64 {
+                    continue;
+                }
+
+                let distance = (dx * dx + dy * dy) as f32;
+                let index = (py as u32 * self.width + px as u32) as usize;
+                self.values[index] += (-distance / (2.0 * SPREAD * SPREAD)).exp();
+            }
+        }
+    }
+
+    fn draw(&self, image: &mut RgbImage, left: u32) {
+        let max = self.values.iter().cloned().fold(0.0, f32::max);
+        if max <= 0.0 {
+            return;
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = self.values[(y * self.width + x) as usize] / max;
+                // The square root keeps single clicks visible next to clusters
+                if value > 0.01 {
+                    image.put_pixel(left + x, y, rgb(ramp(value.sqrt())));
+                }
+            }
+        }
+    }
+}
+
+// Fades from the background into the ramp colors as `value` goes from 0.0 to 1.0
+fn ramp(value: f32) -> [f32; 3] {
+    let stops: Vec<[f32; 3]> = std::iter::once(BACKGROUND).chain(RAMP.iter().copied()).collect();
+    let position = value.min(1.0) * (stops.len() - 1) as f32;
+    let index = (position as usize).min(stops.len() - 2);
+    let t = position - index as f32;
+
+    let (from, to) = (stops[index], stops[index + 1]);
+    [
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t,
+    ]
+}
+
+fn rgb(color: [f32; 3]) -> Rgb<u8> {
+    Rgb([
+        (color[0] * 255.0) as u8,
+        (color[1] * 255.0) as u8,
+        (color[2] * 255.0) as u8,
+    ])
+}
+
+fn put_guide(image: &mut RgbImage, x: f32, y: f32) {
+    if x >= 0.0 && y >= 0.0 && (x as u32) < image.width() && (y as u32) < image.height() {
+        image.put_pixel(x as u32, y as u32, rgb(GUIDE));
+    }
+}
+
+fn draw_circle(image: &mut RgbImage, x: f32, y: f32, radius: f32) {
+    let steps = (radius * 8.0) as u32;
+    for step in 0..steps {
+        let angle = step as f32 / steps as f32 * std::f32::consts::TAU;
+        put_guide(image, x + radius * angle.cos(), y + radius * angle.sin());
+    }
+}
+
+fn draw_cross(image: &mut RgbImage, x: f32, y: f32, size: f32) {
+    for step in -(size as i32)..=size as i32 {
+        put_guide(image, x + step as f32, y);
+        put_guide(image, x, y + step as f32);
+    }
+}
+
+fn draw_rect(image: &mut RgbImage, left: f32, width: f32) {
+    let height = image.height() as f32;
+    for x in 0..width as u32 {
+        put_guide(image, left + x as f32, 0.0);
+        put_guide(image, left + x as f32, height - 1.0);
+    }
+    for y in 0..height as u32 {
+        put_guide(image, left, y as f32);
+        put_guide(image, left + width - 1.0, y as f32);
+    }
+}
@@ -0,0 +1,157 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    modes::GameMode,
+    paths::Paths,
+    stats::Click,
+    storage,
+};
+
+const HIGH_SCORE_FILE: &str = "highscores.json";
+// How many runs are kept per mode
+const MAX_ENTRIES: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunResult {
+    pub mode: GameMode,
+    pub score: u32,
+    // Seconds since the unix epoch
+    pub date: u64,
+    // Seconds
+    pub duration: f32,
+    pub gravity: f32,
+    #[serde(default)]
+    pub hits: u32,
+    #[serde(default)]
+    pub misses: u32,
+    // Seconds from a target spawning until it was hit
+    pub reaction_mean: Option<f32>,
+    pub reaction_median: Option<f32>,
+    pub reaction_best: Option<f32>,
+    // Mean precision of all hits, 1.0 is dead center
+    pub precision: Option<f32>,
+    // Fraction of the held time spent on target in tracking mode
+    pub tracking: Option<f32>,
+    // Seed the targets were placed with, replaying it gives the same targets
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // Daily challenge runs are kept apart from the high scores
+    #[serde(default)]
+    pub daily: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct HighScores {
+    entries: Vec<RunResult>,
+}
+
+// The run that ended most recently, shown on the game over screen. The clicks are kept apart
+// from the session stats, which watching a replay starts over.
+#[derive(Default)]
+pub struct LastRun {
+    pub run: Option<RunResult>,
+    pub clicks: Vec<Click>,
+}
+
+pub struct RunEndEvent(pub RunResult);
+
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let high_scores = HighScores::load(app.world().get_resource::<Paths>().unwrap());
+
+        app.insert_resource(high_scores)
+            .insert_resource(LastRun::default())
+            .add_system(record_run.system())
+            .add_event::<RunEndEvent>();
+    }
+}
+
+impl RunResult {
+    pub fn accuracy(&self) -> f32 {
+        let shots = self.hits + self.misses;
+        if shots == 0 {
+            0.0
+        } else {
+            self.hits as f32 / shots as f32
+        }
+    }
+}
+
+impl HighScores {
+    pub fn load(paths: &Paths) -> Self {
+        storage::load_json(&paths.data_file(HIGH_SCORE_FILE))
+    }
+
+    pub fn save(&self, paths: &Paths) {
+        storage::save_json(&paths.data_file(HIGH_SCORE_FILE), self);
+    }
+
+    // Returns the place the run got in its mode, if it made the table
+    pub fn insert(&mut self, run: RunResult) -> Option<usize> {
+        // Runs with an equal score keep their older place
+        let place = self
+            .top(run.mode)
+            .take_while(|entry| entry.score >= run.score)
+            .count();
+        if place >= MAX_ENTRIES {
+            return None;
+        }
+
+        // Entries are kept sorted by score, highest first
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.score < run.score)
+            .unwrap_or(self.entries.len());
+        let mode = run.mode;
+        self.entries.insert(index, run);
+
+        let mut kept = 0;
+        self.entries.retain(|entry| {
+            if entry.mode != mode {
+                return true;
+            }
+            kept += 1;
+            kept <= MAX_ENTRIES
+        });
+
+        Some(place + 1)
+    }
+
+    pub fn top(&self, mode: GameMode) -> impl Iterator<Item = &RunResult> {
+        self.entries.iter().filter(move |entry| entry.mode == mode)
+    }
+
+    pub fn best(&self, mode: GameMode) -> Option<&RunResult> {
+        self.top(mode).next()
+    }
+}
+
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn record_run(
+    mut ev_run_end: EventReader<RunEndEvent>,
+    mut high_scores: ResMut<HighScores>,
+    paths: Res<Paths>,
+) {
+    for run in ev_run_end.iter() {
+        if run.0.daily {
+            continue;
+        }
+
+        if let Some(place) = high_scores.insert(run.0.clone()) {
+            println!("New high score #{} in {}: {}", place, run.0.mode.name(), run.0.score);
+        }
+        high_scores.save(&paths);
+    }
+}
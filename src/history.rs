@@ -0,0 +1,254 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    daily::date_string,
+    difficulty::{Difficulty, DifficultyProfile},
+    highscore::{RunEndEvent, RunResult},
+    modes::{GameMode, ModeSettings},
+    paths::Paths,
+    spawner::{SpawnPolicy, SpawnScheduler},
+    stats::{Click, SessionStats},
+    storage,
+};
+
+// One finished run per line, lines are only ever added
+const HISTORY_FILE: &str = "history.jsonl";
+
+// A finished run with everything it was played with
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    #[serde(flatten)]
+    pub run: RunResult,
+    // Seconds from a target spawning until it was hit, for every hit
+    #[serde(default)]
+    pub reaction_times: Vec<f32>,
+    // Every click of the run, for the heatmap
+    #[serde(default)]
+    pub clicks: Vec<Click>,
+    pub difficulty: DifficultyProfile,
+    pub spawn: SpawnPolicy,
+    pub mode_settings: ModeSettings,
+    pub sensitivity: f32,
+    pub window_width: f32,
+    pub window_height: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("unknown format {}, expected csv or json", name)),
+        }
+    }
+}
+
+// Every finished run, oldest first
+#[derive(Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let history = History::load(app.world().get_resource::<Paths>().unwrap());
+
+        app.insert_resource(history)
+            .add_system(record_history.system());
+    }
+}
+
+impl History {
+    // Lines that can't be parsed, like one cut short by a crash, are skipped
+    pub fn load(paths: &Paths) -> Self {
+        let path = paths.data_file(HISTORY_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return History::default(),
+            Err(err) => {
+                eprintln!("Could not read {}: {}", path.display(), err);
+                return History::default();
+            }
+        };
+
+        let entries = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(number, line)| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    eprintln!("Skipping line {} of {}: {}", number + 1, path.display(), err);
+                    None
+                }
+            })
+            .collect();
+
+        History { entries }
+    }
+
+    // Adds the run to the end of the file as well
+    pub fn push(&mut self, entry: HistoryEntry, paths: &Paths) {
+        let path = paths.data_file(HISTORY_FILE);
+        let line = serde_json::to_string(&entry).unwrap();
+        if let Err(err) = storage::append_line(&path, &line) {
+            eprintln!("Could not write {}: {}", path.display(), err);
+        }
+
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    // Runs of one mode, oldest first. Daily challenge attempts are left out like they are from
+    // the high scores.
+    pub fn of_mode(&self, mode: GameMode) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.run.mode == mode && !entry.run.daily)
+    }
+}
+
+fn record_history(
+    mut ev_run_end: EventReader<RunEndEvent>,
+    stats: Res<SessionStats>,
+    difficulty: Res<Difficulty>,
+    scheduler: Res<SpawnScheduler>,
+    mode_settings: Res<ModeSettings>,
+    config: Res<Config>,
+    windows: Res<Windows>,
+    mut history: ResMut<History>,
+    paths: Res<Paths>,
+) {
+    let window = windows.get_primary().unwrap();
+
+    for run in ev_run_end.iter() {
+        let entry = HistoryEntry {
+            run: run.0.clone(),
+            reaction_times: stats.reaction_times.clone(),
+            clicks: stats.clicks.clone(),
+            difficulty: difficulty.0.clone(),
+            spawn: scheduler.policy().clone(),
+            mode_settings: mode_settings.clone(),
+            sensitivity: config.sensitivity,
+            window_width: window.width(),
+            window_height: window.height(),
+        };
+
+        history.push(entry, &paths);
+    }
+}
+
+// Writes the history, optionally only one mode, to `out` for charting in other tools
+fn export(
+    entries: &[HistoryEntry],
+    only: Option<GameMode>,
+    format: ExportFormat,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let entries: Vec<&HistoryEntry> = entries
+        .iter()
+        .filter(|entry| only.map_or(true, |only| only == entry.run.mode))
+        .collect();
+
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &entries)?;
+            writeln!(out)
+        }
+        ExportFormat::Csv => {
+            writeln!(
+                out,
+                "date,day,mode,daily,score,duration,hits,misses,accuracy,reaction_mean,\
+                 reaction_median,reaction_best,precision,tracking,gravity,seed,start_gravity,\
+                 gravity_step,gravity_cap,target_scale,spawn_rate,spawn_policy,time_limit,lives,\
+                 sensitivity,window_width,window_height"
+            )?;
+
+            for entry in entries {
+                let run = &entry.run;
+                let difficulty = &entry.difficulty;
+
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                    run.date,
+                    date_string(run.date / 86400),
+                    run.mode.key(),
+                    run.daily,
+                    run.score,
+                    run.duration,
+                    run.hits,
+                    run.misses,
+                    run.accuracy(),
+                    optional(run.reaction_mean),
+                    optional(run.reaction_median),
+                    optional(run.reaction_best),
+                    optional(run.precision),
+                    optional(run.tracking),
+                    run.gravity,
+                    optional(run.seed),
+                    difficulty.start_gravity,
+                    difficulty.gravity_step,
+                    difficulty.gravity_cap,
+                    difficulty.target_scale,
+                    difficulty.spawn_rate,
+                    entry.spawn.name(),
+                    entry.mode_settings.time_limit,
+                    entry.mode_settings.lives,
+                    entry.sensitivity,
+                    entry.window_width,
+                    entry.window_height
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+// Missing values are empty cells
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(String::new, |value| value.to_string())
+}
+
+// Exports to a file, or to stdout without one
+pub fn export_to(
+    paths: &Paths,
+    only: Option<GameMode>,
+    format: ExportFormat,
+    output: Option<&Path>,
+) -> io::Result<()> {
+    let history = History::load(paths);
+    let entries = history.entries();
+
+    match output {
+        Some(path) => {
+            let mut file = io::BufWriter::new(fs::File::create(path)?);
+            export(entries, only, format, &mut file)?;
+            file.flush()
+        }
+        None => export(entries, only, format, &mut io::stdout().lock()),
+    }
+}
@@ -0,0 +1,261 @@
+use bevy::{asset::LoadState, prelude::*, render::texture::TextureFormat};
+use image::{DynamicImage, ImageBuffer};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, Materials};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HitTestSettings {
+    // Test clicks against the shape of the target image instead of a circle
+    pub pixel_accurate: bool,
+    // Pixels of the target image at least this opaque count as the target, from 0.0 to 1.0
+    pub alpha_threshold: f32,
+}
+
+impl Default for HitTestSettings {
+    fn default() -> Self {
+        HitTestSettings {
+            pixel_accurate: true,
+            alpha_threshold: 0.5,
+        }
+    }
+}
+
+// Opacity of every pixel of an image, rows from the top
+pub struct AlphaMask {
+    width: u32,
+    height: u32,
+    alpha: Vec<u8>,
+}
+
+impl AlphaMask {
+    fn from_image(image: &DynamicImage) -> Self {
+        let image = image.to_rgba8();
+
+        AlphaMask {
+            width: image.width(),
+            height: image.height(),
+            alpha: image.pixels().map(|pixel| pixel[3]).collect(),
+        }
+    }
+
+    // Turns the texture the asset server loaded back into an image. These are all the formats
+    // it loads PNGs as, 16 bit textures hold native endian values.
+    fn from_texture(texture: &Texture) -> Option<Self> {
+        let (width, height) = (texture.size.width, texture.size.height);
+        let bytes = || texture.data.clone();
+        let words = || {
+            texture
+                .data
+                .chunks_exact(2)
+                .map(|word| u16::from_ne_bytes([word[0], word[1]]))
+                .collect::<Vec<u16>>()
+        };
+
+        let image = match texture.format {
+            TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => {
+                ImageBuffer::from_raw(width, height, bytes()).map(DynamicImage::ImageRgba8)
+            }
+            TextureFormat::Bgra8UnormSrgb | TextureFormat::Bgra8Unorm => {
+                ImageBuffer::from_raw(width, height, bytes()).map(DynamicImage::ImageBgra8)
+            }
+            TextureFormat::R16Uint => {
+                ImageBuffer::from_raw(width, height, words()).map(DynamicImage::ImageLuma16)
+            }
+            TextureFormat::Rg16Uint => {
+                ImageBuffer::from_raw(width, height, words()).map(DynamicImage::ImageLumaA16)
+            }
+            TextureFormat::Rgba16Uint => {
+                ImageBuffer::from_raw(width, height, words()).map(DynamicImage::ImageRgba16)
+            }
+            _ => None,
+        }?;
+
+        Some(AlphaMask::from_image(&image))
+    }
+
+    // `u` and `v` go from 0.0 to 1.0 across the image, `v` from the top
+    fn alpha_at(&self, u: f32, v: f32) -> Option<u8> {
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return None;
+        }
+
+        let x = (u * self.width as f32) as u32;
+        let y = (v * self.height as f32) as u32;
+
+        Some(self.alpha[(y * self.width + x) as usize])
+    }
+}
+
+// Decides whether a click hits a target, set up from the config and swapped out by the daily
+// challenge and replays
+pub struct HitTest {
+    // Sampled from the target texture once the asset server has loaded it, targets are circles
+    // until then
+    mask: Option<AlphaMask>,
+    // Set once the texture loaded or failed to
+    mask_done: bool,
+    settings: HitTestSettings,
+}
+
+impl HitTest {
+    pub fn settings(&self) -> &HitTestSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: HitTestSettings) {
+        self.settings = settings;
+    }
+
+    // `offset` is from the center of the target to the click and `size` is the size of its
+    // sprite, both in world units
+    pub fn hits(&self, offset: Vec2, size: Vec2) -> bool {
+        match (&self.mask, self.settings.pixel_accurate) {
+            (Some(mask), true) => {
+                let threshold = self.settings.alpha_threshold.max(0.0).min(1.0);
+                // The image is stretched over the sprite, world y goes up and image rows go down
+                let u = offset.x / size.x + 0.5;
+                let v = 0.5 - offset.y / size.y;
+
+                mask.alpha_at(u, v)
+                    .map_or(false, |alpha| alpha >= (threshold * 255.0).round() as u8)
+            }
+            _ => offset.length() <= size.x / 2.0,
+        }
+    }
+}
+
+pub struct HitTestPlugin;
+
+impl Plugin for HitTestPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(hit_test_setup.system())
+            .add_system(load_alpha_mask.system());
+    }
+}
+
+fn hit_test_setup(mut commands: Commands, config: Res<Config>) {
+    commands.insert_resource(HitTest {
+        mask: None,
+        mask_done: false,
+        settings: config.hit_test.clone(),
+    });
+}
+
+fn load_alpha_mask(
+    mut hit_test: ResMut<HitTest>,
+    materials: Res<Materials>,
+    color_materials: Res<Assets<ColorMaterial>>,
+    textures: Res<Assets<Texture>>,
+    asset_server: Res<AssetServer>,
+) {
+    if hit_test.mask_done {
+        return;
+    }
+
+    let handle = match color_materials
+        .get(&materials.target)
+        .and_then(|material| material.texture.clone())
+    {
+        Some(handle) => handle,
+        None => return,
+    };
+
+    if let Some(texture) = textures.get(&handle) {
+        hit_test.mask = AlphaMask::from_texture(texture);
+        if hit_test.mask.is_none() {
+            eprintln!("The target image format can't be read, targets are treated as circles");
+        }
+        hit_test.mask_done = true;
+    } else if asset_server.get_load_state(&handle) == LoadState::Failed {
+        eprintln!("The target image could not be loaded, targets are treated as circles");
+        hit_test.mask_done = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::texture::Extent3d;
+    use image::{Rgba, RgbaImage};
+
+    // 4x4 image, the left half opaque, the right half half transparent
+    fn half_mask() -> AlphaMask {
+        let image = RgbaImage::from_fn(4, 4, |x, _| {
+            Rgba([255, 255, 255, if x < 2 { 255 } else { 128 }])
+        });
+        AlphaMask::from_image(&DynamicImage::ImageRgba8(image))
+    }
+
+    fn hit_test(alpha_threshold: f32) -> HitTest {
+        HitTest {
+            mask: Some(half_mask()),
+            mask_done: true,
+            settings: HitTestSettings {
+                pixel_accurate: true,
+                alpha_threshold,
+            },
+        }
+    }
+
+    #[test]
+    fn mask_is_stretched_over_the_sprite() {
+        let hit_test = hit_test(0.9);
+
+        for size in [100.0, 300.0].iter() {
+            let size = Vec2::splat(*size);
+            // A fifth of the sprite from the center, to either side, then just outside it
+            assert!(hit_test.hits(Vec2::new(-size.x / 5.0, 0.0), size));
+            assert!(!hit_test.hits(Vec2::new(size.x / 5.0, 0.0), size));
+            assert!(!hit_test.hits(Vec2::new(-size.x * 0.51, 0.0), size));
+        }
+        // Inside the large sprite but outside the small one
+        assert!(hit_test.hits(Vec2::new(-100.0, 140.0), Vec2::splat(300.0)));
+        assert!(!hit_test.hits(Vec2::new(-100.0, 140.0), Vec2::splat(100.0)));
+    }
+
+    #[test]
+    fn threshold_decides_which_pixels_count() {
+        let size = Vec2::splat(100.0);
+        let half_transparent = Vec2::new(20.0, 0.0);
+
+        assert!(hit_test(0.5).hits(half_transparent, size));
+        assert!(!hit_test(0.6).hits(half_transparent, size));
+    }
+
+    #[test]
+    fn circle_without_a_mask() {
+        let mut hit_test = hit_test(0.5);
+        hit_test.mask = None;
+        let size = Vec2::splat(100.0);
+
+        assert!(hit_test.hits(Vec2::new(30.0, 30.0), size));
+        assert!(!hit_test.hits(Vec2::new(40.0, 40.0), size));
+    }
+
+    #[test]
+    fn alpha_is_read_from_every_png_format() {
+        let bgra = Texture {
+            data: vec![0, 0, 0, 255, 0, 0, 0, 0],
+            size: Extent3d::new(2, 1, 1),
+            format: TextureFormat::Bgra8UnormSrgb,
+            ..Default::default()
+        };
+        let mut gray_alpha_16 = Vec::new();
+        for value in [0u16, u16::MAX, 0, 0].iter() {
+            gray_alpha_16.extend_from_slice(&value.to_ne_bytes());
+        }
+        let gray_alpha_16 = Texture {
+            data: gray_alpha_16,
+            size: Extent3d::new(2, 1, 1),
+            format: TextureFormat::Rg16Uint,
+            ..Default::default()
+        };
+
+        for texture in [bgra, gray_alpha_16].iter() {
+            let mask = AlphaMask::from_texture(texture).unwrap();
+            assert_eq!(mask.alpha, vec![255, 0]);
+        }
+    }
+}
@@ -1,11 +1,42 @@
-use bevy::prelude::*;
-use rand::Rng;
-
-use crate::{Crosshair, GameState, Gravity, Materials, Score, ScoreText, Speed, Target};
+use bevy::{input::mouse::MouseMotion, prelude::*};
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    config::{Config, CrosshairStyle},
+    cursor::CursorWorld,
+    daily::DailyRun,
+    difficulty::{Difficulty, DifficultyProfile},
+    highscore::{now_timestamp, HighScores, LastRun, RunEndEvent, RunResult},
+    hit_test::HitTest,
+    modes::{GameMode, ModeSettings},
+    physics::{fall_step, SimClock},
+    playfield::{follow_resize, screen_bounds, PLAYFIELD_HEIGHT, PLAYFIELD_WIDTH},
+    rng::{FixedSeed, GameRng},
+    scoring::{precision, ScoringRings},
+    spawner::SpawnScheduler,
+    stats::{Click, SessionStats},
+    AccuracyText, Crosshair, GameState, Gravity, Lives, Materials, ModeText, Score, ScoreText,
+    SpawnedAt, Speed, Target,
+};
+
+// Width of a target in playfield units before the difficulty scales it, the size targets had
+// in a 1280x720 window
+const TARGET_WIDTH: f32 = 192.0;
 
 pub struct InGamePlugin;
 
-struct ScoreEvent(u32);
+pub struct ScoreEvent(pub u32);
+struct EndRunEvent;
+// Where the crosshair points in logical pixels from the bottom left corner of the window, see
+// `CursorWorld` for world coordinates. The cursor is locked while playing so this follows the
+// mouse movement scaled by the sensitivity.
+pub struct Aim(pub Vec2);
+// State of the left mouse button this frame
+#[derive(Default, Clone, Copy)]
+pub struct Shot {
+    pub pressed: bool,
+    pub just_pressed: bool,
+}
 
 impl Plugin for InGamePlugin {
     fn build(&self, app: &mut AppBuilder) {
@@ -13,27 +44,27 @@ impl Plugin for InGamePlugin {
             // Setup systems
             .add_system_set(
                 SystemSet::on_enter(GameState::InGame)
-                    .with_system(game_startup.system())
+                    .with_system(game_startup.system().label("game_startup"))
                     .with_system(hide_cursor.system())
                     .with_system(spawn_crosshair.system())
-                    .with_system(spawn_text.system()),
+                    .with_system(spawn_text.system().after("game_startup")),
             )
             .add_system_set(
                 SystemSet::on_resume(GameState::InGame)
-                    .with_system(game_startup.system())
+                    .with_system(game_startup.system().label("game_startup"))
                     .with_system(hide_cursor.system())
                     .with_system(spawn_crosshair.system())
-                    .with_system(spawn_text.system()),
+                    .with_system(spawn_text.system().after("game_startup")),
             )
             // Update system
             .add_system_set(
                 SystemSet::on_update(GameState::InGame)
-                    .with_system(target_movement.system())
-                    .with_system(target_click.system())
-                    .with_system(target_reset.system())
-                    .with_system(move_crosshair.system())
-                    .with_system(update_text.system()),
+                    .with_system(advance_sim_clock.system().label("sim_clock"))
+                    .with_system(aim_follow_window.system().before("input"))
+                    .with_system(read_input.system().label("input")),
             )
+            .add_system_set(simulation(GameState::InGame))
+            .add_system_set(simulation(GameState::Replay))
             // Shutdown systems
             .add_system_set(
                 SystemSet::on_pause(GameState::InGame)
@@ -49,34 +80,155 @@ impl Plugin for InGamePlugin {
                     .with_system(despawn_crosshair.system())
                     .with_system(despawn_text.system()),
             )
+            // A replay plays the run again with the input from the recording
+            .add_system_set(
+                SystemSet::on_enter(GameState::Replay)
+                    .with_system(
+                        game_startup
+                            .system()
+                            .label("game_startup")
+                            .after("start_playback"),
+                    )
+                    .with_system(spawn_crosshair.system())
+                    .with_system(spawn_text.system().after("game_startup")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Replay)
+                    .with_system(target_despawn.system())
+                    .with_system(despawn_crosshair.system())
+                    .with_system(despawn_text.system()),
+            )
+            .insert_resource(Aim(Vec2::ZERO))
+            .insert_resource(Shot::default())
             // Add event
-            .add_event::<ScoreEvent>();
+            .add_event::<ScoreEvent>()
+            .add_event::<EndRunEvent>();
             
     }
 }
 
-fn game_startup(mut commands: Commands, materials: Res<Materials>, windows: Res<Windows>) {
-    spawn_target(&windows, &mut commands, &materials);
+// Everything that plays out a run, shared by playing and watching a replay. The clock and the
+// input come from systems labelled "sim_clock" and "input", which differ between the two. The
+// input is turned into world coordinates by the cursor plugin.
+// The steps run in a fixed order, so a replay plays out exactly like the run it recorded:
+// spawn, movement, click, reset, end conditions. The tracking plugin uses the same labels.
+fn simulation(state: GameState) -> SystemSet {
+    SystemSet::on_update(state)
+        .with_system(
+            spawn_scheduled_targets
+                .system()
+                .label("spawn")
+                .after("sim_clock"),
+        )
+        .with_system(target_movement.system().label("movement").after("spawn"))
+        .with_system(
+            target_click
+                .system()
+                .label("click")
+                .after("movement")
+                .after("cursor_world"),
+        )
+        .with_system(target_reset.system().label("reset").after("click"))
+        .with_system(
+            mode_end_conditions
+                .system()
+                .label("end_conditions")
+                .after("reset"),
+        )
+        .with_system(finish_run.system().after("end_conditions"))
+        .with_system(move_crosshair.system().after("cursor_world"))
+        .with_system(update_text.system())
+        .with_system(update_accuracy_text.system())
+        .with_system(update_mode_text.system())
+}
+
+fn game_startup(
+    mut clock: ResMut<SimClock>,
+    mut stats: ResMut<SessionStats>,
+    mut scheduler: ResMut<SpawnScheduler>,
+    mut lives: ResMut<Lives>,
+    mode_settings: Res<ModeSettings>,
+    mut gravity: ResMut<Gravity>,
+    mut score: ResMut<Score>,
+    mut ev_score: EventWriter<ScoreEvent>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<GameRng>,
+    fixed_seed: Res<FixedSeed>,
+) {
+    *rng = GameRng::from_seed(fixed_seed.next_seed());
+    gravity.0 = difficulty.0.start_gravity;
+    // Not left to target_despawn, the last target may already be gone when the run ends
+    score.0 = 0;
+    ev_score.send(ScoreEvent(score.0));
+    clock.reset();
+    *stats = SessionStats::default();
+    scheduler.reset();
+    lives.0 = mode_settings.lives;
+}
+
+fn spawn_scheduled_targets(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    clock: Res<SimClock>,
+    mut scheduler: ResMut<SpawnScheduler>,
+    query: Query<Entity, With<Target>>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<GameRng>,
+) {
+    // Tracking mode spawns its own moving target
+    if *mode == GameMode::Tracking {
+        return;
+    }
+
+    let alive = query.iter().count() as u32;
+
+    for _ in 0..scheduler.due(clock.pending_duration(), alive) {
+        spawn_target(
+            &mut commands,
+            &materials,
+            &difficulty.0,
+            &mut rng.rng,
+            clock.elapsed(),
+        );
+    }
 }
 
-fn hide_cursor(mut windows: ResMut<Windows>) {
+fn hide_cursor(mut windows: ResMut<Windows>, mut aim: ResMut<Aim>) {
     let window = windows.get_primary_mut().unwrap();
 
     window.set_cursor_visibility(false);
+    window.set_cursor_lock_mode(true);
+    aim.0 = Vec2::new(window.width() / 2.0, window.height() / 2.0);
 }
 
 fn show_cursor(mut windows: ResMut<Windows>) {
     let window = windows.get_primary_mut().unwrap();
 
+    window.set_cursor_lock_mode(false);
     window.set_cursor_visibility(true);
 }
 
-fn spawn_crosshair(mut commands: Commands, materials: Res<Materials>) {
+fn spawn_crosshair(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    mut color_material: ResMut<Assets<ColorMaterial>>,
+    config: Res<Config>,
+) {
+    let crosshair = &config.crosshair;
+    let material = match crosshair.style {
+        CrosshairStyle::Image => color_material.add(ColorMaterial::modulated_texture(
+            materials.crosshair_texture.clone(),
+            crosshair.color(),
+        )),
+        CrosshairStyle::Dot => color_material.add(crosshair.color().into()),
+    };
+
     commands
         .spawn_bundle(SpriteBundle {
-            material: materials.crosshair.clone(),
+            material,
             transform: Transform::from_xyz(0.0, 0.0, 1.0),
-            sprite: Sprite::new(Vec2::new(60.0, 60.0)),
+            sprite: Sprite::new(Vec2::new(crosshair.size, crosshair.size)),
             ..Default::default()
         })
         .insert(Crosshair);
@@ -88,7 +240,12 @@ fn despawn_crosshair(mut commands: Commands, mut query: Query<Entity, With<Cross
     }
 }
 
-fn spawn_text(mut commands: Commands, mut ev_score: EventWriter<ScoreEvent>, score: Res<Score>,) {
+fn spawn_text(
+    mut commands: Commands,
+    mut ev_score: EventWriter<ScoreEvent>,
+    score: Res<Score>,
+    materials: Res<Materials>,
+) {
     commands
     .spawn_bundle(TextBundle {
         style: Style {
@@ -118,11 +275,44 @@ fn spawn_text(mut commands: Commands, mut ev_score: EventWriter<ScoreEvent>, sco
     })
     .insert(ScoreText);
 
+    commands
+        .spawn_bundle(hud_text(&materials, 70.0))
+        .insert(AccuracyText);
+    commands
+        .spawn_bundle(hud_text(&materials, 105.0))
+        .insert(ModeText);
+
     ev_score.send(ScoreEvent(score.0));
 }
 
+// Small line of text below the score
+pub fn hud_text(materials: &Materials, top: f32) -> TextBundle {
+    TextBundle {
+        style: Style {
+            align_self: AlignSelf::FlexStart,
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(top),
+                left: Val::Px(5.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: materials.font.clone(),
+                font_size: 30.0,
+                color: Color::rgb(0.75, 0.75, 0.75),
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    }
+}
+
 fn despawn_text(
-    mut query: Query<Entity, With<ScoreText>>,
+    mut query: Query<Entity, Or<(With<ScoreText>, With<AccuracyText>, With<ModeText>)>>,
     mut commands: Commands,
 ) {
     for entity in query.iter_mut() {
@@ -130,77 +320,144 @@ fn despawn_text(
     }
 }
 
-fn move_crosshair(windows: ResMut<Windows>, mut query: Query<&mut Transform, With<Crosshair>>) {
+fn read_input(
+    mut motion: EventReader<MouseMotion>,
+    btn: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    config: Res<Config>,
+    mut aim: ResMut<Aim>,
+    mut shot: ResMut<Shot>,
+) {
     let window = windows.get_primary().unwrap();
+    // The crosshair stays on the playfield
+    let (min, max) = screen_bounds(window);
+
+    for ev in motion.iter() {
+        // Mouse motion goes down the screen, world coordinates go up
+        aim.0 += Vec2::new(ev.delta.x, -ev.delta.y) * config.sensitivity;
+    }
 
-    if let Some(mouse_pos) = window.cursor_position() {
+    aim.0 = aim.0.clamp(min, max);
+
+    shot.pressed = btn.pressed(MouseButton::Left);
+    shot.just_pressed = btn.just_pressed(MouseButton::Left);
+}
+
+// The aim is kept in window pixels, when the window is resized or switches between windowed and
+// fullscreen it is moved so the crosshair stays on the same spot of the playfield. Targets are
+// placed on the playfield and don't move.
+fn aim_follow_window(
+    windows: Res<Windows>,
+    mut aim: ResMut<Aim>,
+    mut laid_out_for: Local<Option<Vec2>>,
+) {
+    let window = windows.get_primary().unwrap();
+    let size = Vec2::new(window.width(), window.height());
+
+    if let Some(previous) = *laid_out_for {
+        if previous != size {
+            aim.0 = follow_resize(aim.0, previous, size);
+        }
+    }
+    *laid_out_for = Some(size);
+}
+
+// Distance from the center of a target to `position`
+pub fn target_distance(tf: &Transform, position: Vec2) -> f32 {
+    Vec2::from(tf.translation).distance(position)
+}
+
+fn move_crosshair(
+    cursor_world: Res<CursorWorld>,
+    mut query: Query<&mut Transform, With<Crosshair>>,
+) {
+    if let Some(position) = cursor_world.0 {
         for mut tf in query.iter_mut() {
-            tf.translation.x = mouse_pos.x - window.width() / 2.0;
-            tf.translation.y = mouse_pos.y - window.height() / 2.0;
+            tf.translation.x = position.x;
+            tf.translation.y = position.y;
         }
     }
 }
 
+fn advance_sim_clock(mut clock: ResMut<SimClock>, time: Res<Time>) {
+    clock.advance(time.delta_seconds());
+}
+
 fn target_movement(
-    windows: Res<Windows>,
     gravity: Res<Gravity>,
     mut query: Query<(&mut Transform, &mut Speed), With<Target>>,
-    time: Res<Time>,
+    clock: Res<SimClock>,
 ) {
-    for (mut tf, mut acc) in query.iter_mut() {
-        let window = windows.get_primary().unwrap();
-        acc.0 += gravity.0;
-        tf.translation.y -= acc.0 * (window.height() / 1000.0) * time.delta_seconds();
+    let scale = PLAYFIELD_HEIGHT / 1000.0;
+
+    for (mut tf, mut speed) in query.iter_mut() {
+        for _ in 0..clock.pending() {
+            fall_step(&mut speed.0, &mut tf.translation.y, gravity.0, scale);
+        }
     }
 }
 
 fn target_click(
-    btn: Res<Input<MouseButton>>,
-    windows: Res<Windows>,
-    mut query: Query<(&Transform, &Sprite, Entity), With<Target>>,
+    shot: Res<Shot>,
+    cursor_world: Res<CursorWorld>,
+    hit_test: Res<HitTest>,
+    mut query: Query<(&Transform, &Sprite, &SpawnedAt, Entity), With<Target>>,
     mut commands: Commands,
     mut gravity: ResMut<Gravity>,
     mut score: ResMut<Score>,
-    materials: Res<Materials>,
     mut ev_score: EventWriter<ScoreEvent>,
+    mut stats: ResMut<SessionStats>,
+    clock: Res<SimClock>,
+    rings: Res<ScoringRings>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
 ) {
-    if btn.just_pressed(MouseButton::Left) {
-        let window = windows.get_primary().unwrap();
-
-        if let Some(mouse_pos) = window.cursor_position() {
-            for (tf, sprite, entity) in query.iter_mut() {
-                let distance = Vec2::from(tf.translation)
-                    .distance(mouse_pos - Vec2::new(window.width() / 2.0, window.height() / 2.0));
-
-                if distance <= sprite.size.x / 2.0 {
-                    commands.entity(entity).despawn();
-                    let mut rng = rand::thread_rng();
-                    let window = windows.get_primary().unwrap();
-
-                    let target_width = (window.width() / 30.0 * window.height() / 30.0) / 8.0;
-
-                    let target_x = rng.gen_range(
-                        -window.width() / 2.0 + target_width / 2.0
-                            ..window.width() / 2.0 - target_width / 2.0,
-                    );
-                    let target_y = window.height() / 2.0 - target_width / 2.0;
-
-                    commands
-                        .spawn_bundle(SpriteBundle {
-                            material: materials.target.clone(),
-                            transform: Transform::from_xyz(target_x, target_y, 0.0),
-                            sprite: Sprite::new(Vec2::new(target_width, target_width)),
-                            ..Default::default()
-                        })
-                        .insert(Target)
-                        .insert(Speed(0.0));
-
-                    gravity.0 += 0.1;
-                    score.0 += 1;
-                    ev_score.send(ScoreEvent(score.0));
-                }
+    // Tracking targets are held, not clicked
+    if *mode == GameMode::Tracking {
+        return;
+    }
+
+    if let (true, Some(position)) = (shot.just_pressed, cursor_world.0) {
+        let mut hit = false;
+        // Offset from the center of the closest target in target radii, for the click heatmap
+        let mut closest: Option<Vec2> = None;
+
+        for (tf, sprite, spawned_at, entity) in query.iter_mut() {
+            let offset = position - Vec2::from(tf.translation);
+            let radius = sprite.size.x / 2.0;
+            // Distance from the center relative to the target radius
+            let relative_distance = target_distance(tf, position) / radius;
+
+            if closest.map_or(true, |closest| relative_distance < closest.length()) {
+                closest = Some(offset / radius);
+            }
+
+            if hit_test.hits(offset, sprite.size) {
+                commands.entity(entity).despawn();
+
+                gravity.0 = difficulty.0.next_gravity(gravity.0);
+                // The shape of the target can reach past its radius, that still counts as the edge
+                score.0 += rings.points(relative_distance.min(1.0));
+                ev_score.send(ScoreEvent(score.0));
+                stats.reaction_times.push(clock.elapsed() - spawned_at.0);
+                stats.precisions.push(precision(relative_distance));
+                hit = true;
+                closest = Some(offset / radius);
+                // One click only ever hits one target
+                break;
             }
         }
+
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        stats.clicks.push(Click {
+            position: position.into(),
+            offset: closest.map(|offset| offset.into()),
+            hit,
+        });
     }
 }
 
@@ -208,8 +465,11 @@ fn update_text (
     mut ev_levelup: EventReader<ScoreEvent>,
     mut query: Query<&mut Text, With<ScoreText>>,
     materials: Res<Materials>,
+    high_scores: Res<HighScores>,
+    mode: Res<GameMode>,
 ) {
     for score in ev_levelup.iter() {
+        let best = high_scores.best(*mode).map_or(0, |run| run.score);
 
         for mut text in query.iter_mut()  {
             text.sections = vec![
@@ -221,66 +481,202 @@ fn update_text (
                         color: Color::rgb(0.75, 0.75, 0.75),
                     },
                 },
+                TextSection {
+                    value: format!("  best {}", best.max(score.0)),
+                    style: TextStyle {
+                        font: materials.font.clone(),
+                        font_size: 30.0,
+                        color: Color::rgb(0.75, 0.75, 0.75),
+                    },
+                },
             ];
         }
     }
 }
 
+fn update_accuracy_text(
+    stats: Res<SessionStats>,
+    mut query: Query<(&mut Text, ChangeTrackers<AccuracyText>)>,
+) {
+    for (mut text, tracker) in query.iter_mut() {
+        if stats.is_changed() || tracker.is_added() {
+            text.sections[0].value = match stats.tracking() {
+                Some(tracking) => format!("{:.0}% on target", tracking * 100.0),
+                None => format!(
+                    "{} hit  {} missed  {:.0}%",
+                    stats.hits,
+                    stats.misses,
+                    stats.accuracy() * 100.0
+                ),
+            };
+        }
+    }
+}
+
+fn update_mode_text(
+    mode: Res<GameMode>,
+    mode_settings: Res<ModeSettings>,
+    lives: Res<Lives>,
+    clock: Res<SimClock>,
+    mut query: Query<&mut Text, With<ModeText>>,
+) {
+    let value = match *mode {
+        GameMode::Classic => String::new(),
+        GameMode::TimeAttack | GameMode::Tracking => format!(
+            "{:.0} s left",
+            (mode_settings.time_limit - clock.elapsed()).max(0.0).ceil()
+        ),
+        GameMode::Survival => format!("{} lives", lives.0),
+        GameMode::Zen => "Press enter to finish".to_string(),
+    };
+
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
 fn target_reset(
-    mut query: Query<(Entity, &mut Transform), With<Target>>,
-    windows: Res<Windows>,
+    query: Query<(Entity, &Transform), With<Target>>,
     mut commands: Commands,
-    mut gravity: ResMut<Gravity>,
-    mut score: ResMut<Score>,
-    materials: Res<Materials>,
-    mut ev_score: EventWriter<ScoreEvent>,
+    mode: Res<GameMode>,
+    mut lives: ResMut<Lives>,
+    mut ev_end_run: EventWriter<EndRunEvent>,
 ) {
-    let window = windows.get_primary().unwrap();
-    for (entity, tf) in query.iter_mut() {
-        if tf.translation.y < -window.height() / 2.0 {
+    for (entity, tf) in query.iter() {
+        if tf.translation.y < -PLAYFIELD_HEIGHT / 2.0 {
             commands.entity(entity).despawn();
-            gravity.0 = 1.0;
-            println!("Score: {}", score.0);
-            score.0 = 0;
 
-            ev_score.send(ScoreEvent(score.0));
+            match *mode {
+                GameMode::Classic => ev_end_run.send(EndRunEvent),
+                GameMode::Survival => {
+                    lives.0 = lives.0.saturating_sub(1);
+                    if lives.0 == 0 {
+                        ev_end_run.send(EndRunEvent);
+                    }
+                }
+                GameMode::TimeAttack | GameMode::Zen | GameMode::Tracking => {}
+            }
+        }
+    }
+}
 
-            spawn_target(&windows, &mut commands, &materials);
+fn mode_end_conditions(
+    mode: Res<GameMode>,
+    mode_settings: Res<ModeSettings>,
+    clock: Res<SimClock>,
+    kb: Res<Input<KeyCode>>,
+    config: Res<Config>,
+    mut ev_end_run: EventWriter<EndRunEvent>,
+) {
+    match *mode {
+        GameMode::TimeAttack | GameMode::Tracking
+            if clock.elapsed() >= mode_settings.time_limit =>
+        {
+            ev_end_run.send(EndRunEvent)
         }
+        GameMode::Zen if kb.just_pressed(config.keybinds.finish_run) => ev_end_run.send(EndRunEvent),
+        _ => {}
     }
 }
 
+fn finish_run(
+    mut ev_end_run: EventReader<EndRunEvent>,
+    mode: Res<GameMode>,
+    gravity: Res<Gravity>,
+    score: Res<Score>,
+    clock: Res<SimClock>,
+    stats: Res<SessionStats>,
+    rng: Res<GameRng>,
+    daily: Res<DailyRun>,
+    mut last_run: ResMut<LastRun>,
+    mut ev_run_end: EventWriter<RunEndEvent>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    // Several things can end the run in the same frame, it only ends once
+    if ev_end_run.iter().count() == 0 {
+        return;
+    }
+
+    // A replay runs until its recording does, the run it shows was already recorded
+    if *game_state.current() == GameState::Replay {
+        return;
+    }
+
+    // Ending the run wins over a state change already queued this frame, like pressing pause
+    if game_state.overwrite_set(GameState::GameOver).is_err() {
+        return;
+    }
+
+    // The targets, score and gravity are reset when the game state is left
+    let run = RunResult {
+        mode: *mode,
+        score: score.0,
+        date: now_timestamp(),
+        duration: clock.elapsed(),
+        gravity: gravity.0,
+        hits: stats.hits,
+        misses: stats.misses,
+        reaction_mean: stats.mean_reaction(),
+        reaction_median: stats.median_reaction(),
+        reaction_best: stats.best_reaction(),
+        precision: stats.mean_precision(),
+        tracking: stats.tracking(),
+        seed: Some(rng.seed),
+        daily: daily.is_active(),
+    };
+
+    last_run.run = Some(run.clone());
+    last_run.clicks = stats.clicks.clone();
+    ev_run_end.send(RunEndEvent(run));
+}
+
 fn target_despawn(
     mut query: Query<Entity, With<Target>>,
     mut commands: Commands,
     mut gravity: ResMut<Gravity>,
     mut score: ResMut<Score>,
     mut ev_score: EventWriter<ScoreEvent>,
+    difficulty: Res<Difficulty>,
 ) {
     for entity in query.iter_mut() {
         commands.entity(entity).despawn();
-        gravity.0 = 1.0;
+        gravity.0 = difficulty.0.start_gravity;
         score.0 = 0;
 
         ev_score.send(ScoreEvent(score.0));
     }
 }
 
-fn spawn_target(windows: &Res<Windows>, commands: &mut Commands, materials: &Res<Materials>) {
-    let mut rng = rand::thread_rng();
-    let window = windows.get_primary().unwrap();
-    let target_width = (window.width() / 30.0 * window.height() / 30.0) / 8.0;
+pub fn target_width(difficulty: &DifficultyProfile) -> f32 {
+    TARGET_WIDTH * difficulty.target_scale
+}
+
+pub fn target_bundle(materials: &Materials, x: f32, y: f32, width: f32) -> SpriteBundle {
+    SpriteBundle {
+        material: materials.target.clone(),
+        transform: Transform::from_xyz(x, y, 0.0),
+        sprite: Sprite::new(Vec2::new(width, width)),
+        ..Default::default()
+    }
+}
+
+fn spawn_target(
+    commands: &mut Commands,
+    materials: &Res<Materials>,
+    difficulty: &DifficultyProfile,
+    rng: &mut StdRng,
+    spawned_at: f32,
+) {
+    let target_width = target_width(difficulty);
     let target_x = rng.gen_range(
-        -window.width() / 2.0 + target_width / 2.0..window.width() / 2.0 - target_width / 2.0,
+        -PLAYFIELD_WIDTH / 2.0 + target_width / 2.0..PLAYFIELD_WIDTH / 2.0 - target_width / 2.0,
     );
-    let target_y = window.height() / 2.0 - target_width / 2.0;
+    let target_y = PLAYFIELD_HEIGHT / 2.0 - target_width / 2.0;
     commands
-        .spawn_bundle(SpriteBundle {
-            material: materials.target.clone(),
-            transform: Transform::from_xyz(target_x, target_y, 0.0),
-            sprite: Sprite::new(Vec2::new(target_width, target_width)),
-            ..Default::default()
-        })
+        .spawn_bundle(target_bundle(materials, target_x, target_y, target_width))
         .insert(Target)
-        .insert(Speed(0.0));
+        .insert(Speed(0.0))
+        .insert(SpawnedAt(spawned_at));
 }
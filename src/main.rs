@@ -1,6 +1,8 @@
-#![windows_subsystem = "windows"]
+// Release builds on Windows don't open a console window next to the game, debug builds keep it
+// for the logs
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 //TODO Add score counter on screen
-//TODO Add high score
+//DONE Add high score
 //DONE Add main menu
 //DONE Add pause screen§
 //DONE Add material to target
@@ -9,20 +11,62 @@
 //DONE Make the start button start the game
 //DONE Mouse should be a crosshair
 
+mod cli;
+mod config;
+mod cursor;
+mod daily;
+mod difficulty;
+mod game_over;
+mod heatmap;
+mod highscore;
+mod history;
+mod hit_test;
 mod ingame;
 mod main_menu;
+mod modes;
 mod pause;
-
-use std::fs;
+mod paths;
+mod physics;
+mod playfield;
+mod replay;
+mod rng;
+mod scoring;
+mod settings;
+mod spawner;
+mod stats;
+mod stats_screen;
+mod storage;
+mod tracking;
+mod ui;
+
+use std::process;
 
 use bevy::{prelude::*, window::WindowMode};
-
-use serde::{Serialize, Deserialize};
-
-
+use structopt::StructOpt;
+
+use cli::Cli;
+use config::get_config;
+use cursor::{CursorPlugin, MainCamera};
+use daily::DailyPlugin;
+use difficulty::Difficulty;
+use game_over::GameOverPlugin;
+use highscore::HighScorePlugin;
+use history::HistoryPlugin;
+use hit_test::HitTestPlugin;
 use ingame::InGamePlugin;
 use main_menu::MainMenuPlugin;
 use pause::PausePlugin;
+use paths::Paths;
+use physics::SimClock;
+use playfield::PlayfieldPlugin;
+use replay::{QueuedReplay, Replay, ReplayPlugin};
+use rng::{FixedSeed, GameRng};
+use scoring::ScoringRings;
+use settings::SettingsPlugin;
+use spawner::SpawnScheduler;
+use stats::SessionStats;
+use stats_screen::StatsScreenPlugin;
+use tracking::TrackingPlugin;
 
 struct Target;
 struct Title;
@@ -31,19 +75,24 @@ struct StartBtn;
 struct Speed(f32);
 struct Gravity(f32);
 struct Score(u32);
-struct FullscreenEnabled(bool);
+// Lives left in survival mode
+struct Lives(u32);
+// Simulated time at which a target was spawned
+struct SpawnedAt(f32);
 struct Crosshair;
 struct ScoreText;
+struct AccuracyText;
+struct ModeText;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 enum GameState {
     InGame,
     MainMenu,
     Paused,
-}
-#[derive(Serialize, Deserialize)]
-struct Config {
-    fullscreen: bool,
+    GameOver,
+    Settings,
+    Replay,
+    Stats,
 }
 
 pub struct Materials {
@@ -54,8 +103,10 @@ pub struct Materials {
     fullscreen_text: Handle<ColorMaterial>,
     button: Handle<ColorMaterial>,
     button_pressed: Handle<ColorMaterial>,
-    crosshair: Handle<ColorMaterial>,
+    // Tinted with the crosshair color from the config
+    crosshair_texture: Handle<Texture>,
     exit: Handle<ColorMaterial>,
+    menu_button: Handle<ColorMaterial>,
     font: Handle<Font>,
 }
 
@@ -63,18 +114,10 @@ fn setup(
     mut commands: Commands,
     mut color_material: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
-    mut windows: ResMut<Windows>,
-    fullscreen: Res<FullscreenEnabled>,
 ) {
-    let window = windows.get_primary_mut().unwrap();
-
-    if fullscreen.0 {
-        window.set_mode(WindowMode::BorderlessFullscreen);
-    } else {
-        window.set_mode(WindowMode::Windowed);
-    }
-
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
     commands.spawn_bundle(UiCameraBundle::default());
 
     commands.insert_resource(Materials {
@@ -85,51 +128,123 @@ fn setup(
         fullscreen_text: color_material.add(asset_server.load("fullscreen.png").into()),
         button: color_material.add(asset_server.load("button.png").into()),
         button_pressed: color_material.add(asset_server.load("button_pressed.png").into()),
-        crosshair: color_material.add(asset_server.load("crosshair.png").into()),
+        crosshair_texture: asset_server.load("crosshair.png"),
         exit: color_material.add(asset_server.load("exit.png").into()),
+        menu_button: color_material.add(Color::rgb(0.75, 0.75, 0.75).into()),
         font: asset_server.load("font.ttf"),
     });
 }
 
-fn get_config() -> Config {
-    // Read the file
-    let contents = fs::read_to_string("config.json")
-    .expect("Something went wrong reading the file");
-
-    let config: Config = serde_json::from_str(contents.as_str()).unwrap();
+// Without a console of its own the game writes to the one it was started from, so the help,
+// argument errors and subcommands still print something. Started from the explorer there is
+// none to attach to and the output is dropped like before.
+#[cfg(all(windows, not(debug_assertions)))]
+fn attach_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
 
-    config
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
 }
 
-fn set_title(mut windows: ResMut<Windows>,) {
-    let window = windows.get_primary_mut().unwrap();
-
-    window.set_title("POCOP".to_string());
-}
+#[cfg(not(all(windows, not(debug_assertions))))]
+fn attach_console() {}
 
 fn main() {
-    let config = get_config();
+    attach_console();
+    let cli = Cli::from_args();
+    let paths = Paths::new(&cli);
+
+    if let Some(command) = &cli.command {
+        process::exit(cli::run_command(command, &paths));
+    }
+
+    // Only a normal start moves old files over, not a config picked on the command line
+    if cli.config.is_none() && cli.config_dir.is_none() {
+        paths.migrate_legacy_files();
+    }
+
+    // A replay given on the command line is played right after the start
+    let queued_replay = match &cli.replay {
+        Some(path) => match Replay::load(path) {
+            Ok(replay) => QueuedReplay(Some(replay)),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                process::exit(1);
+            }
+        },
+        None => QueuedReplay(None),
+    };
+
+    let config = get_config(&paths.config_file);
+    let difficulty = config.difficulty.profile();
+
+    // Command line overrides only last for this session and never end up in the config
+    let window_mode = if cli.windowed {
+        WindowMode::Windowed
+    } else {
+        config.window.mode.into()
+    };
+    let fixed_seed = FixedSeed(cli.seed.or(config.seed));
+    let mut mode_settings = config.modes.clone();
+    if let Some(duration) = cli.duration {
+        mode_settings.time_limit = duration;
+    }
 
     App::build()
+        // The window is created from this when DefaultPlugins are added
+        .insert_resource(WindowDescriptor {
+            title: "POCOP".to_string(),
+            width: config.window.width,
+            height: config.window.height,
+            mode: window_mode,
+            ..Default::default()
+        })
+        // Needed by the plugins that load and save files
+        .insert_resource(paths)
         //
         // Plugins
         .add_plugins(DefaultPlugins)
         .add_plugin(MainMenuPlugin)
         .add_plugin(PausePlugin)
         .add_plugin(InGamePlugin)
+        .add_plugin(HighScorePlugin)
+        .add_plugin(GameOverPlugin)
+        .add_plugin(TrackingPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(DailyPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(StatsScreenPlugin)
+        .add_plugin(HitTestPlugin)
+        .add_plugin(CursorPlugin)
+        .add_plugin(PlayfieldPlugin)
         //
         // Resources
         .insert_resource(ClearColor(Color::rgb(0.927, 0.927, 0.927)))
-        .insert_resource(Gravity(1.0))
+        .insert_resource(Gravity(difficulty.start_gravity))
         .insert_resource(Score(0))
-        .insert_resource(FullscreenEnabled(config.fullscreen))
+        .insert_resource(SimClock::default())
+        .insert_resource(SessionStats::default())
+        .insert_resource(SpawnScheduler::new(
+            config.spawn.clone(),
+            difficulty.spawn_rate,
+        ))
+        .insert_resource(ScoringRings::new(config.rings.clone()))
+        .insert_resource(cli.mode.unwrap_or_default())
+        .insert_resource(Lives(mode_settings.lives))
+        .insert_resource(mode_settings)
+        .insert_resource(GameRng::from_seed(fixed_seed.next_seed()))
+        .insert_resource(fixed_seed)
+        .insert_resource(Difficulty(difficulty))
+        .insert_resource(queued_replay)
+        .insert_resource(config)
         //
         // Add state
         .add_state(GameState::MainMenu)
         //
         // Startup systems
         .add_startup_system(setup.system())
-        .add_startup_system(set_title.system())
         // InGame set
         //
         // Running it
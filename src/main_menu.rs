@@ -1,6 +1,15 @@
 use bevy::prelude::*;
 
-use crate::{GameState, MainScreenRelated, Materials, StartBtn, Title};
+use crate::{
+    daily::{button_label, DailyButton, DailyResults},
+    modes::GameMode,
+    ui::spawn_text_button,
+    GameState, MainScreenRelated, Materials, StartBtn, Title,
+};
+
+struct ModeButton;
+struct SettingsButton;
+struct StatsButton;
 
 pub struct MainMenuPlugin;
 
@@ -23,7 +32,11 @@ impl Plugin for MainMenuPlugin {
                 .with_system(despawn_start_button.system()),
         )
         .add_system_set(
-            SystemSet::on_update(GameState::MainMenu).with_system(switch_to_game.system()),
+            SystemSet::on_update(GameState::MainMenu)
+                .with_system(switch_to_game.system())
+                .with_system(mode_picker.system())
+                .with_system(open_settings.system())
+                .with_system(open_stats.system()),
         );
     }
 }
@@ -32,6 +45,8 @@ fn main_menu_setup(
     mut commands: Commands,
     mut color_material: ResMut<Assets<ColorMaterial>>,
     ui_materials: Res<Materials>,
+    mode: Res<GameMode>,
+    daily_results: Res<DailyResults>,
 ) {
     commands
         .spawn_bundle(NodeBundle {
@@ -92,12 +107,29 @@ fn main_menu_setup(
                 })
                 .insert(StartBtn)
                 .insert(MainScreenRelated);
+
+            spawn_text_button(parent, &ui_materials, &mode_label(*mode))
+                .insert(ModeButton)
+                .insert(MainScreenRelated);
+            spawn_text_button(parent, &ui_materials, &button_label(&daily_results))
+                .insert(DailyButton)
+                .insert(MainScreenRelated);
+            spawn_text_button(parent, &ui_materials, "Statistics")
+                .insert(StatsButton)
+                .insert(MainScreenRelated);
+            spawn_text_button(parent, &ui_materials, "Settings")
+                .insert(SettingsButton)
+                .insert(MainScreenRelated);
         });
 }
 
+fn mode_label(mode: GameMode) -> String {
+    format!("Mode: {}", mode.name())
+}
+
 fn despawn_title(mut query: Query<Entity, With<MainScreenRelated>>, mut commands: Commands) {
     for entity in query.iter_mut() {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
     }
 }
 
@@ -114,9 +146,49 @@ fn switch_to_game(
     for interaction in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
-                game_state.set(GameState::InGame).unwrap();
+                let _ = game_state.set(GameState::InGame);
             }
             _ => (),
         }
     }
 }
+
+fn mode_picker(
+    mut mode: ResMut<GameMode>,
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<ModeButton>)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, children) in interaction_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            *mode = mode.next();
+
+            for child in children.iter() {
+                if let Ok(mut text) = text_query.get_mut(*child) {
+                    text.sections[0].value = mode_label(*mode);
+                }
+            }
+        }
+    }
+}
+
+fn open_settings(
+    mut game_state: ResMut<State<GameState>>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<SettingsButton>)>,
+) {
+    for interaction in interaction_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            let _ = game_state.push(GameState::Settings);
+        }
+    }
+}
+
+fn open_stats(
+    mut game_state: ResMut<State<GameState>>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<StatsButton>)>,
+) {
+    for interaction in interaction_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            let _ = game_state.push(GameState::Stats);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    // The run ends as soon as a target drops
+    Classic,
+    // Hit as many targets as possible before the time runs out
+    TimeAttack,
+    // Every dropped target costs a life
+    Survival,
+    // Nothing ends the run, press enter to stop
+    Zen,
+    // Keep the crosshair on a moving target with the mouse button held
+    Tracking,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Classic
+    }
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::Survival,
+        GameMode::Zen,
+        GameMode::Tracking,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack => "Time attack",
+            GameMode::Survival => "Survival",
+            GameMode::Zen => "Zen",
+            GameMode::Tracking => "Tracking",
+        }
+    }
+
+    // Name used in the config, on the command line and in exported files
+    pub fn key(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::TimeAttack => "time_attack",
+            GameMode::Survival => "survival",
+            GameMode::Zen => "zen",
+            GameMode::Tracking => "tracking",
+        }
+    }
+
+    pub fn next(&self) -> GameMode {
+        let index = GameMode::ALL.iter().position(|mode| mode == self).unwrap();
+
+        GameMode::ALL[(index + 1) % GameMode::ALL.len()]
+    }
+}
+
+// Parses the same names the config and the high score file use
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match GameMode::ALL.iter().find(|mode| mode.key() == name) {
+            Some(mode) => Ok(*mode),
+            None => Err(format!(
+                "unknown mode {}, expected one of classic, time_attack, survival, zen or tracking",
+                name
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModeSettings {
+    // Seconds
+    #[serde(default = "default_time_limit")]
+    pub time_limit: f32,
+    #[serde(default = "default_lives")]
+    pub lives: u32,
+}
+
+fn default_time_limit() -> f32 {
+    60.0
+}
+
+fn default_lives() -> u32 {
+    3
+}
+
+impl Default for ModeSettings {
+    fn default() -> Self {
+        ModeSettings {
+            time_limit: default_time_limit(),
+            lives: default_lives(),
+        }
+    }
+}
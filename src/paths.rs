@@ -0,0 +1,99 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::cli::Cli;
+
+const APP_DIR: &str = "pocop";
+const CONFIG_FILE: &str = "config.json";
+// Files that older versions kept in the working directory
+const LEGACY_CONFIG_FILES: [&str; 1] = [CONFIG_FILE];
+const LEGACY_DATA_FILES: [&str; 1] = ["highscores.json"];
+
+// Where the config and the save data are kept for this session
+pub struct Paths {
+    pub config_dir: PathBuf,
+    pub config_file: PathBuf,
+    pub data_dir: PathBuf,
+}
+
+impl Paths {
+    // The config directory is `--config-dir`, $POCOP_CONFIG_DIR or the user config directory of
+    // the platform (~/.config/pocop on Linux), in that order. `--config` picks the file itself.
+    // The data directory is `--data-dir`, $POCOP_DATA_DIR or the user data directory of the
+    // platform (~/.local/share/pocop on Linux).
+    pub fn new(cli: &Cli) -> Self {
+        let config_dir = resolve_dir(&cli.config_dir, "POCOP_CONFIG_DIR", dirs::config_dir());
+        let config_file = cli
+            .config
+            .clone()
+            .unwrap_or_else(|| config_dir.join(CONFIG_FILE));
+        let data_dir = resolve_dir(&cli.data_dir, "POCOP_DATA_DIR", dirs::data_dir());
+
+        Paths {
+            config_dir,
+            config_file,
+            data_dir,
+        }
+    }
+
+    pub fn data_file(&self, name: &str) -> PathBuf {
+        self.data_dir.join(name)
+    }
+
+    // Moves files left in the working directory by older versions into the user directories.
+    // Files already in the user directories are never overwritten.
+    pub fn migrate_legacy_files(&self) {
+        for (names, dir) in [
+            (&LEGACY_CONFIG_FILES[..], &self.config_dir),
+            (&LEGACY_DATA_FILES[..], &self.data_dir),
+        ]
+        .iter()
+        {
+            for name in names.iter() {
+                let legacy = PathBuf::from(name);
+                let target = dir.join(name);
+
+                // Also skips the move when the user directory is the working directory
+                if !legacy.is_file() || target.exists() {
+                    continue;
+                }
+
+                match move_file(&legacy, &target) {
+                    Ok(()) => println!("Moved {} to {}", name, target.display()),
+                    Err(err) => {
+                        eprintln!("Could not move {} to {}: {}", name, target.display(), err)
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn resolve_dir(flag: &Option<PathBuf>, var: &str, platform_dir: Option<PathBuf>) -> PathBuf {
+    if let Some(dir) = flag {
+        return dir.clone();
+    }
+
+    if let Some(dir) = env::var_os(var).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+
+    // Without a home directory fall back to the working directory like older versions did
+    platform_dir.map_or_else(|| PathBuf::from("."), |dir| dir.join(APP_DIR))
+}
+
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Renaming fails across file systems, copy the file there instead
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+
+    Ok(())
+}
@@ -1,13 +1,17 @@
-use std::{fs::File, io::Write};
-
 use bevy::{app::AppExit, prelude::*, window::WindowMode};
 
-use crate::{FullscreenEnabled, GameState, Materials, get_config};
+use crate::{
+    config::{save_config, Config, WindowModeSetting},
+    paths::Paths,
+    ui::spawn_text_button,
+    GameState, Materials,
+};
 
 struct PausedScreenRelated;
 struct ExitGameButton;
 struct FullscreenButton;
-struct FullscreenEvent(bool);
+struct SettingsButton;
+struct FullscreenEvent;
 
 // Plugin
 pub struct PausePlugin;
@@ -21,23 +25,37 @@ impl Plugin for PausePlugin {
                 SystemSet::on_enter(GameState::Paused).with_system(paused_setup.system()),
             )
             .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(paused_exit.system()))
+            // The settings screen goes on top of the pause screen
+            .add_system_set(SystemSet::on_pause(GameState::Paused).with_system(paused_exit.system()))
+            .add_system_set(
+                SystemSet::on_resume(GameState::Paused).with_system(paused_setup.system()),
+            )
             .add_system_set(
                 SystemSet::on_update(GameState::Paused)
                 .with_system(fullscreen_listener.system())
+                .with_system(settings_listener.system())
                 .with_system(exit_listener.system()),
             )
             .add_event::<FullscreenEvent>();
     }
 }
 
-fn pause_handler(kb: Res<Input<KeyCode>>, mut game_state: ResMut<State<GameState>>) {
-    if kb.just_pressed(KeyCode::Escape) {
+fn pause_handler(
+    kb: Res<Input<KeyCode>>,
+    config: Res<Config>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    // A state change already queued this frame, like the end of the run or a clicked button,
+    // goes first and the key press is dropped
+    if kb.just_pressed(config.keybinds.pause) {
         match game_state.current() {
-            GameState::Paused => {
-                game_state.pop().unwrap();
+            GameState::Paused | GameState::Settings | GameState::Stats => {
+                let _ = game_state.pop();
             }
+            // The pause key stops watching a replay instead
+            GameState::Replay => {}
             _ => {
-                game_state.push(GameState::Paused).unwrap();
+                let _ = game_state.push(GameState::Paused);
             }
         }
     }
@@ -47,9 +65,11 @@ fn paused_setup(
     mut commands: Commands,
     mut color_material: ResMut<Assets<ColorMaterial>>,
     ui_materials: Res<Materials>,
-    fullscreen_enabled: Res<FullscreenEnabled>,
+    windows: Res<Windows>,
 ) {
-    let check_material = if fullscreen_enabled.0 {
+    // The window mode can differ from the config for a session started with --windowed
+    let window = windows.get_primary().unwrap();
+    let check_material = if window.mode() != WindowMode::Windowed {
         ui_materials.button_pressed.clone()
     } else {
         ui_materials.button.clone()
@@ -149,7 +169,11 @@ fn paused_setup(
                         .insert(PausedScreenRelated);
                 })
                 .insert(PausedScreenRelated);
-                
+
+                spawn_text_button(parent, &ui_materials, "Settings")
+                    .insert(SettingsButton)
+                    .insert(PausedScreenRelated);
+
                 let exit_button_height = 162.0;
 
                 parent
@@ -179,49 +203,43 @@ fn paused_setup(
 
 fn paused_exit(mut commands: Commands, mut query: Query<Entity, With<PausedScreenRelated>>) {
     for entity in query.iter_mut() {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
     }
 }
 
-fn set_fullscreen_config(mut fullscreen_event: EventReader<FullscreenEvent>,) {
-    for ev in fullscreen_event.iter() {
-        let mut cur_config = get_config();
-
-        cur_config.fullscreen = ev.0;
-
-        let j = serde_json::to_string(&cur_config).unwrap();
-
-        let mut file = File::create("config.json").unwrap();
-
-        file.write(j.as_bytes()).unwrap();
+fn set_fullscreen_config(
+    mut fullscreen_event: EventReader<FullscreenEvent>,
+    config: Res<Config>,
+    paths: Res<Paths>,
+) {
+    if fullscreen_event.iter().count() > 0 {
+        save_config(&config, &paths.config_file);
     }
 }
 
 fn fullscreen_listener(
     mut query: Query<
         (&Interaction, &mut Handle<ColorMaterial>),
-        (Changed<Interaction>, With<Button>),
+        (Changed<Interaction>, With<FullscreenButton>),
     >,
     ui_materials: Res<Materials>,
     mut windows: ResMut<Windows>,
-    mut fullscreen_enabled: ResMut<FullscreenEnabled>,
+    mut config: ResMut<Config>,
     mut ev_score: EventWriter<FullscreenEvent>,
 ) {
     for (interaction, mut material) in query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
                 let window = windows.get_primary_mut().unwrap();
-                if fullscreen_enabled.0 {
+                if window.mode() != WindowMode::Windowed {
                     *material = ui_materials.button.clone();
-                    fullscreen_enabled.0 = false;
-
-                    window.set_mode(WindowMode::Windowed);
+                    config.window.mode = WindowModeSetting::Windowed;
                 } else {
                     *material = ui_materials.button_pressed.clone();
-                    fullscreen_enabled.0 = true;
-                    window.set_mode(WindowMode::BorderlessFullscreen);
+                    config.window.mode = WindowModeSetting::Borderless;
                 }
-                ev_score.send(FullscreenEvent(fullscreen_enabled.0));
+                window.set_mode(config.window.mode.into());
+                ev_score.send(FullscreenEvent);
             }
             Interaction::Hovered => {}
             Interaction::None => {}
@@ -229,6 +247,17 @@ fn fullscreen_listener(
     }
 }
 
+fn settings_listener(
+    query: Query<&Interaction, (Changed<Interaction>, With<SettingsButton>)>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    for interaction in query.iter() {
+        if let Interaction::Clicked = *interaction {
+            let _ = game_state.push(GameState::Settings);
+        }
+    }
+}
+
 fn exit_listener(
     mut query: Query<
         &Interaction,
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+// Length of one simulation step in seconds. Targets move in whole steps so
+// the same run plays out identically no matter the frame rate.
+pub const TICK: f32 = 1.0 / 120.0;
+
+// Leftover time this close to a whole step counts as one
+const ROUNDING_SLACK: f32 = TICK / 1000.0;
+
+// Gravity used to be added to the speed once per frame, tuned at 60 fps
+const GRAVITY_RATE: f32 = 60.0;
+
+#[derive(Default)]
+pub struct SimClock {
+    accumulator: f32,
+    ticks: u64,
+    pending: u32,
+}
+
+impl SimClock {
+    pub fn reset(&mut self) {
+        *self = SimClock::default();
+    }
+
+    // Adds the frame time and returns how many steps should be simulated this frame
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.accumulator += delta;
+        // Frame times that add up to a whole step can fall short of it by a rounding error,
+        // that step still runs this frame
+        self.pending = ((self.accumulator + ROUNDING_SLACK) / TICK) as u32;
+        self.accumulator -= self.pending as f32 * TICK;
+        self.ticks += self.pending as u64;
+
+        self.pending
+    }
+
+    // Simulates a given number of steps, used to play back a recording
+    pub fn advance_ticks(&mut self, ticks: u32) {
+        self.pending = ticks;
+        self.ticks += ticks as u64;
+    }
+
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+
+    pub fn pending_duration(&self) -> Duration {
+        Duration::from_secs_f32(self.pending as f32 * TICK)
+    }
+
+    // Simulated seconds since the clock was reset
+    pub fn elapsed(&self) -> f32 {
+        self.ticks as f32 * TICK
+    }
+}
+
+// Moves a falling target one step. `scale` converts the fall distance to world units.
+pub fn fall_step(speed: &mut f32, y: &mut f32, gravity: f32, scale: f32) {
+    *speed += gravity * GRAVITY_RATE * TICK;
+    *y -= *speed * scale * TICK;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays `seconds` of a falling target at `fps` frames per second
+    fn simulate(fps: u32, seconds: u32) -> (u64, f32, f32) {
+        let mut clock = SimClock::default();
+        let (mut speed, mut y) = (0.0, 0.0);
+
+        for _ in 0..fps * seconds {
+            for _ in 0..clock.advance(1.0 / fps as f32) {
+                fall_step(&mut speed, &mut y, 1.5, 1.08);
+            }
+        }
+
+        (clock.ticks, speed, y)
+    }
+
+    #[test]
+    fn trajectory_does_not_depend_on_frame_rate() {
+        let expected = simulate(60, 3);
+        assert_eq!(expected.0, 3 * 120);
+
+        for fps in [30, 144, 240].iter() {
+            assert_eq!(simulate(*fps, 3), expected, "{} fps", fps);
+        }
+    }
+}
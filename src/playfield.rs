@@ -0,0 +1,124 @@
+use bevy::{
+    prelude::*,
+    render::camera::{Camera, CameraProjection, OrthographicProjection},
+    window::{WindowId, WindowResized},
+};
+
+use crate::cursor::MainCamera;
+
+// The game is played on a playfield of this many world units whatever the size of the window.
+// The camera scales it to fit and bars cover the rest of the window.
+pub const PLAYFIELD_WIDTH: f32 = 1920.0;
+pub const PLAYFIELD_HEIGHT: f32 = 1080.0;
+const BAR_COLOR: [f32; 3] = [0.75, 0.75, 0.75];
+// Drawn over the targets and the crosshair
+const BAR_Z: f32 = 10.0;
+
+pub struct PlayfieldPlugin;
+
+impl Plugin for PlayfieldPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(spawn_bars.system()).add_system(
+            fit_camera
+                .system()
+                .label("playfield_camera")
+                .before("cursor_world"),
+        );
+    }
+}
+
+pub fn playfield_size() -> Vec2 {
+    Vec2::new(PLAYFIELD_WIDTH, PLAYFIELD_HEIGHT)
+}
+
+fn window_size(window: &Window) -> Vec2 {
+    Vec2::new(window.width(), window.height())
+}
+
+// Logical pixels a world unit takes up in a window of `size`
+fn fit(size: Vec2) -> f32 {
+    (size.x / PLAYFIELD_WIDTH).min(size.y / PLAYFIELD_HEIGHT)
+}
+
+// Screen positions are in logical pixels from the bottom left corner of the window like
+// `Window::cursor_position`, the playfield is centered on the origin
+fn to_playfield(size: Vec2, screen: Vec2) -> Vec2 {
+    (screen - size / 2.0) / fit(size)
+}
+
+fn to_screen(size: Vec2, point: Vec2) -> Vec2 {
+    size / 2.0 + point * fit(size)
+}
+
+pub fn pixels_per_unit(window: &Window) -> f32 {
+    fit(window_size(window))
+}
+
+// World units the window shows, the playfield and the bars around it
+pub fn visible_size(window: &Window) -> Vec2 {
+    window_size(window) / pixels_per_unit(window)
+}
+
+pub fn playfield_to_screen(window: &Window, point: Vec2) -> Vec2 {
+    to_screen(window_size(window), point)
+}
+
+// Where a screen position has to move when the window is resized from `from` to `to` logical
+// pixels to stay on the same spot of the playfield
+pub fn follow_resize(screen: Vec2, from: Vec2, to: Vec2) -> Vec2 {
+    to_screen(to, to_playfield(from, screen))
+}
+
+// Corners of the playfield in logical pixels, bottom left first
+pub fn screen_bounds(window: &Window) -> (Vec2, Vec2) {
+    (
+        playfield_to_screen(window, -playfield_size() / 2.0),
+        playfield_to_screen(window, playfield_size() / 2.0),
+    )
+}
+
+// One bar on every side, long enough to cover any window shape
+fn spawn_bars(mut commands: Commands, mut color_material: ResMut<Assets<ColorMaterial>>) {
+    let material = color_material.add(Color::rgb(BAR_COLOR[0], BAR_COLOR[1], BAR_COLOR[2]).into());
+    let size = playfield_size() * 20.0;
+    let offsets = [
+        Vec2::new(-(PLAYFIELD_WIDTH + size.x) / 2.0, 0.0),
+        Vec2::new((PLAYFIELD_WIDTH + size.x) / 2.0, 0.0),
+        Vec2::new(0.0, -(PLAYFIELD_HEIGHT + size.y) / 2.0),
+        Vec2::new(0.0, (PLAYFIELD_HEIGHT + size.y) / 2.0),
+    ];
+
+    for offset in offsets.iter() {
+        commands.spawn_bundle(SpriteBundle {
+            material: material.clone(),
+            transform: Transform::from_translation(offset.extend(BAR_Z)),
+            sprite: Sprite::new(size),
+            ..Default::default()
+        });
+    }
+}
+
+// Zooms the camera so the whole playfield fits the window. Bevy only sizes the projection when
+// the window changes, the scale on top of that is kept up to date here. Resizes are handled
+// right away so the pointer is converted with the new size in the same frame.
+fn fit_camera(
+    mut resized: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut query: Query<(&mut Camera, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let resized: Vec<WindowId> = resized.iter().map(|event| event.id).collect();
+
+    for (mut camera, mut projection) in query.iter_mut() {
+        let window = match windows.get(camera.window) {
+            Some(window) => window,
+            None => continue,
+        };
+        let scale = 1.0 / pixels_per_unit(window);
+
+        if projection.scale != scale || resized.contains(&window.id()) {
+            projection.scale = scale;
+            projection.update(window.width(), window.height());
+            camera.projection_matrix = projection.get_projection_matrix();
+        }
+    }
+}
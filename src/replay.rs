@@ -0,0 +1,677 @@
+use std::{
+    convert::TryInto,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    cursor::CursorWorld,
+    difficulty::{Difficulty, DifficultyProfile},
+    highscore::{now_timestamp, LastRun},
+    hit_test::{HitTest, HitTestSettings},
+    ingame::{hud_text, Shot},
+    modes::{GameMode, ModeSettings},
+    paths::Paths,
+    physics::SimClock,
+    rng::{FixedSeed, GameRng},
+    scoring::{Ring, ScoringRings},
+    spawner::{SpawnPolicy, SpawnScheduler},
+    storage, GameState, Materials,
+};
+
+const REPLAY_DIR: &str = "replays";
+// Older replays are deleted once there are more than this
+const MAX_REPLAYS: usize = 50;
+const MAGIC: &[u8; 4] = b"PCRP";
+// Version 3 stores the aim on the playfield, so replays play back the same in any window.
+// Version 4 adds the hit test settings to the header.
+const FORMAT_VERSION: u8 = 4;
+// Ticks as u16, aim as two f32 and a byte of button flags
+const FRAME_SIZE: usize = 11;
+const CLICKED: u8 = 1;
+const HELD: u8 = 2;
+// Simulated seconds a shot marker stays on screen
+const MARKER_LIFETIME: f32 = 1.0;
+
+// Everything a run depends on besides the input, so playing it back gives the same targets
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReplayHeader {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub mode_settings: ModeSettings,
+    pub difficulty: DifficultyProfile,
+    pub spawn: SpawnPolicy,
+    pub rings: Vec<Ring>,
+    pub hit_test: HitTestSettings,
+    // Seconds since the unix epoch
+    pub date: u64,
+}
+
+// Input of one rendered frame. A frame runs all of its simulation steps first and handles the
+// input after them, both while playing and while watching, so the same batches play out the
+// same way and nothing is gained by storing every step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayFrame {
+    // Simulation steps run in the frame
+    pub ticks: u16,
+    // Crosshair position in world coordinates, the exact value the simulation used. World units
+    // are playfield units, so it doesn't depend on the window.
+    pub aim: Vec2,
+    pub clicked: bool,
+    pub held: bool,
+}
+
+#[derive(Clone)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "could not read the replay: {}", err),
+            ReplayError::Parse(err) => write!(f, "the replay header is invalid: {}", err),
+            ReplayError::Invalid(reason) => write!(f, "not a valid replay: {}", reason),
+        }
+    }
+}
+
+// The file starts with "PCRP" and a version byte, then the header as JSON and the frames, both
+// prefixed with their length as a little endian u32
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let bytes = fs::read(path).map_err(ReplayError::Io)?;
+
+        Replay::decode(&bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        storage::write_atomic(path, self.encode())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let header = serde_json::to_vec(&self.header).unwrap();
+        let mut bytes = Vec::with_capacity(13 + header.len() + self.frames.len() * FRAME_SIZE);
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+
+        for frame in self.frames.iter() {
+            let mut flags = 0;
+            if frame.clicked {
+                flags |= CLICKED;
+            }
+            if frame.held {
+                flags |= HELD;
+            }
+
+            bytes.extend_from_slice(&frame.ticks.to_le_bytes());
+            bytes.extend_from_slice(&frame.aim.x.to_le_bytes());
+            bytes.extend_from_slice(&frame.aim.y.to_le_bytes());
+            bytes.push(flags);
+        }
+
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = Reader(bytes);
+
+        if reader.take(4)? != MAGIC {
+            return Err(ReplayError::Invalid("unknown file type"));
+        }
+        if reader.take(1)?[0] != FORMAT_VERSION {
+            return Err(ReplayError::Invalid("recorded by another version of the game"));
+        }
+
+        let header_len = reader.u32()? as usize;
+        let header = serde_json::from_slice(reader.take(header_len)?).map_err(ReplayError::Parse)?;
+
+        let count = reader.u32()? as usize;
+        if reader.0.len() != count * FRAME_SIZE {
+            return Err(ReplayError::Invalid("the frames don't match their count"));
+        }
+
+        let frames = reader
+            .0
+            .chunks_exact(FRAME_SIZE)
+            .map(|chunk| ReplayFrame {
+                ticks: u16::from_le_bytes(chunk[0..2].try_into().unwrap()),
+                aim: Vec2::new(
+                    f32::from_le_bytes(chunk[2..6].try_into().unwrap()),
+                    f32::from_le_bytes(chunk[6..10].try_into().unwrap()),
+                ),
+                clicked: chunk[10] & CLICKED != 0,
+                held: chunk[10] & HELD != 0,
+            })
+            .collect();
+
+        Ok(Replay { header, frames })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReplayError> {
+        if self.0.len() < len {
+            return Err(ReplayError::Invalid("the file is cut short"));
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, ReplayError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+// The run being recorded, None while not playing or after a pause restarted the run
+#[derive(Default)]
+struct Recorder(Option<Replay>);
+
+// The replay of the run that ended most recently
+#[derive(Default)]
+pub struct LastReplay(pub Option<Replay>);
+
+// A replay waiting to be watched, from the game over screen or the command line
+#[derive(Default)]
+pub struct QueuedReplay(pub Option<Replay>);
+
+// The screen a replay was started from, it goes back there when it ends
+struct ReturnTo(GameState);
+
+// Settings the replay replaced, put back when it ends
+struct Stash {
+    mode: GameMode,
+    mode_settings: ModeSettings,
+    fixed_seed: Option<u64>,
+    difficulty: DifficultyProfile,
+    scheduler: SpawnScheduler,
+    rings: ScoringRings,
+    hit_test: HitTestSettings,
+}
+
+struct Playback {
+    replay: Replay,
+    // Index of the next frame to play
+    frame: usize,
+    paused: bool,
+    return_to: GameState,
+    stash: Stash,
+    marker_material: Handle<ColorMaterial>,
+}
+
+// Where a recorded click landed, with the simulated time it happened at
+struct ShotMarker(f32);
+struct ReplayText;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Recorder::default())
+            .insert_resource(LastReplay::default())
+            .insert_resource(ReturnTo(GameState::MainMenu))
+            .add_system_set(
+                SystemSet::on_enter(GameState::InGame)
+                    .with_system(start_recording.system().after("game_startup")),
+            )
+            .add_system_set(
+                SystemSet::on_resume(GameState::InGame)
+                    .with_system(start_recording.system().after("game_startup")),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame).with_system(
+                    record_frame
+                        .system()
+                        .after("sim_clock")
+                        .after("cursor_world"),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::InGame).with_system(discard_recording.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(save_recording.system()),
+            )
+            // Watching
+            .add_system_set(
+                SystemSet::on_update(GameState::MainMenu).with_system(watch_queued.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver).with_system(watch_queued.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Replay)
+                    .with_system(start_playback.system().label("start_playback"))
+                    .with_system(spawn_replay_text.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Replay)
+                    .with_system(play_frame.system().label("sim_clock").label("input"))
+                    .with_system(mark_shots.system().after("cursor_world"))
+                    .with_system(expire_markers.system().after("sim_clock"))
+                    .with_system(update_replay_text.system().after("sim_clock")),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Replay).with_system(end_playback.system()),
+            );
+    }
+}
+
+fn start_recording(
+    mut recorder: ResMut<Recorder>,
+    rng: Res<GameRng>,
+    mode: Res<GameMode>,
+    mode_settings: Res<ModeSettings>,
+    difficulty: Res<Difficulty>,
+    scheduler: Res<SpawnScheduler>,
+    rings: Res<ScoringRings>,
+    hit_test: Res<HitTest>,
+) {
+    recorder.0 = Some(Replay {
+        header: ReplayHeader {
+            seed: rng.seed,
+            mode: *mode,
+            mode_settings: mode_settings.clone(),
+            difficulty: difficulty.0.clone(),
+            spawn: scheduler.policy().clone(),
+            rings: rings.rings().to_vec(),
+            hit_test: hit_test.settings().clone(),
+            date: now_timestamp(),
+        },
+        frames: Vec::new(),
+    });
+}
+
+fn record_frame(
+    mut recorder: ResMut<Recorder>,
+    clock: Res<SimClock>,
+    cursor_world: Res<CursorWorld>,
+    shot: Res<Shot>,
+) {
+    let replay = match &mut recorder.0 {
+        Some(replay) => replay,
+        None => return,
+    };
+    // Always set while playing, the crosshair is the pointer
+    let aim = cursor_world.0.unwrap_or_default();
+
+    // A long stall is split over several frames, the click goes with the last one
+    let mut ticks = clock.pending();
+    while ticks > u16::MAX as u32 {
+        replay.frames.push(ReplayFrame {
+            ticks: u16::MAX,
+            aim,
+            clicked: false,
+            held: shot.pressed,
+        });
+        ticks -= u16::MAX as u32;
+    }
+
+    replay.frames.push(ReplayFrame {
+        ticks: ticks as u16,
+        aim,
+        clicked: shot.just_pressed,
+        held: shot.pressed,
+    });
+}
+
+// Pausing restarts the run, the recording starts over with it
+fn discard_recording(mut recorder: ResMut<Recorder>) {
+    recorder.0 = None;
+}
+
+fn save_recording(
+    mut recorder: ResMut<Recorder>,
+    mut last_replay: ResMut<LastReplay>,
+    last_run: Res<LastRun>,
+    paths: Res<Paths>,
+) {
+    let replay = match recorder.0.take() {
+        Some(replay) => replay,
+        None => return,
+    };
+
+    let mode = last_run.run.as_ref().map_or(replay.header.mode, |run| run.mode);
+    let dir = paths.data_file(REPLAY_DIR);
+    let path = replay_path(&dir, &replay.header, mode);
+
+    match replay.save(&path) {
+        Ok(()) => prune_replays(&dir),
+        Err(err) => eprintln!("Could not save the replay to {}: {}", path.display(), err),
+    }
+
+    last_replay.0 = Some(replay);
+}
+
+// Runs can end within the same second, even with the same seed when it is fixed, so a number is
+// added until the name is free rather than replacing the earlier recording
+fn replay_path(dir: &Path, header: &ReplayHeader, mode: GameMode) -> PathBuf {
+    let stem = format!("{}-{}-{:016x}", header.date, mode.key(), header.seed);
+    let mut path = dir.join(format!("{}.replay", stem));
+    let mut n = 1;
+
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("{}-{}.replay", stem, n));
+    }
+
+    path
+}
+
+// File names start with the date, so sorting them puts the oldest first
+fn prune_replays(dir: &Path) {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == "replay"))
+            .collect(),
+        Err(_) => return,
+    };
+
+    if files.len() <= MAX_REPLAYS {
+        return;
+    }
+
+    files.sort();
+    for path in files.iter().take(files.len() - MAX_REPLAYS) {
+        if let Err(err) = fs::remove_file(path) {
+            eprintln!("Could not delete the old replay {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn watch_queued(
+    queued: Res<QueuedReplay>,
+    mut return_to: ResMut<ReturnTo>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    // When another screen change is queued the replay stays queued and starts on a later frame
+    if queued.0.is_some() {
+        return_to.0 = game_state.current().clone();
+        let _ = game_state.set(GameState::Replay);
+    }
+}
+
+// Swaps the session settings for the ones the replay was recorded with, before the run starts
+fn start_playback(
+    mut commands: Commands,
+    mut queued: ResMut<QueuedReplay>,
+    return_to: Res<ReturnTo>,
+    mut color_material: ResMut<Assets<ColorMaterial>>,
+    mut mode: ResMut<GameMode>,
+    mut mode_settings: ResMut<ModeSettings>,
+    mut fixed_seed: ResMut<FixedSeed>,
+    mut difficulty: ResMut<Difficulty>,
+    mut scheduler: ResMut<SpawnScheduler>,
+    mut rings: ResMut<ScoringRings>,
+    mut hit_test: ResMut<HitTest>,
+) {
+    let replay = match queued.0.take() {
+        Some(replay) => replay,
+        None => return,
+    };
+    let header = &replay.header;
+
+    let stash = Stash {
+        mode: *mode,
+        mode_settings: mode_settings.clone(),
+        fixed_seed: fixed_seed.0,
+        difficulty: difficulty.0.clone(),
+        scheduler: scheduler.clone(),
+        rings: rings.clone(),
+        hit_test: hit_test.settings().clone(),
+    };
+
+    *mode = header.mode;
+    *mode_settings = header.mode_settings.clone();
+    fixed_seed.0 = Some(header.seed);
+    *scheduler = SpawnScheduler::new(header.spawn.clone(), header.difficulty.spawn_rate);
+    difficulty.0 = header.difficulty.clone().clamped();
+    *rings = ScoringRings::new(header.rings.clone());
+    hit_test.set_settings(header.hit_test.clone());
+
+    commands.insert_resource(Playback {
+        replay,
+        frame: 0,
+        paused: false,
+        return_to: return_to.0.clone(),
+        stash,
+        marker_material: color_material.add(Color::rgba(0.9, 0.1, 0.1, 0.8).into()),
+    });
+}
+
+fn spawn_replay_text(mut commands: Commands, materials: Res<Materials>) {
+    commands
+        .spawn_bundle(hud_text(&materials, 140.0))
+        .insert(ReplayText);
+}
+
+// Feeds the recorded input to the simulation in place of the clock and the mouse. The aim goes
+// straight to `CursorWorld`, the cursor plugin leaves it alone while watching.
+// Space pauses, the right arrow steps one frame while paused and the pause key stops watching.
+fn play_frame(
+    playback: Option<ResMut<Playback>>,
+    kb: Res<Input<KeyCode>>,
+    config: Res<Config>,
+    mut clock: ResMut<SimClock>,
+    mut cursor_world: ResMut<CursorWorld>,
+    mut shot: ResMut<Shot>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    clock.advance_ticks(0);
+    *shot = Shot::default();
+
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => {
+            let _ = game_state.set(GameState::MainMenu);
+            return;
+        }
+    };
+
+    if kb.just_pressed(config.keybinds.pause) {
+        let _ = game_state.set(playback.return_to.clone());
+        return;
+    }
+    if kb.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if playback.paused && !kb.just_pressed(KeyCode::Right) {
+        return;
+    }
+
+    let frame = match playback.replay.frames.get(playback.frame) {
+        Some(frame) => *frame,
+        None => {
+            // Tried again on the next frame if another change got there first
+            let _ = game_state.set(playback.return_to.clone());
+            return;
+        }
+    };
+    playback.frame += 1;
+
+    clock.advance_ticks(frame.ticks as u32);
+    cursor_world.0 = Some(frame.aim);
+    shot.pressed = frame.held;
+    shot.just_pressed = frame.clicked;
+}
+
+fn mark_shots(
+    mut commands: Commands,
+    playback: Option<Res<Playback>>,
+    shot: Res<Shot>,
+    cursor_world: Res<CursorWorld>,
+    clock: Res<SimClock>,
+) {
+    if let (Some(playback), true, Some(position)) = (playback, shot.just_pressed, cursor_world.0) {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: playback.marker_material.clone(),
+                transform: Transform::from_xyz(position.x, position.y, 2.0),
+                sprite: Sprite::new(Vec2::new(6.0, 6.0)),
+                ..Default::default()
+            })
+            .insert(ShotMarker(clock.elapsed()));
+    }
+}
+
+fn expire_markers(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    query: Query<(Entity, &ShotMarker)>,
+) {
+    for (entity, marker) in query.iter() {
+        if clock.elapsed() - marker.0 > MARKER_LIFETIME {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn update_replay_text(
+    playback: Option<Res<Playback>>,
+    mut query: Query<&mut Text, With<ReplayText>>,
+) {
+    let playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    let value = format!(
+        "Replay frame {} of {}{}",
+        playback.frame,
+        playback.replay.frames.len(),
+        if playback.paused { ", paused" } else { "" }
+    );
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn end_playback(
+    mut commands: Commands,
+    query: Query<Entity, Or<(With<ShotMarker>, With<ReplayText>)>>,
+    playback: Option<Res<Playback>>,
+    mut mode: ResMut<GameMode>,
+    mut mode_settings: ResMut<ModeSettings>,
+    mut fixed_seed: ResMut<FixedSeed>,
+    mut difficulty: ResMut<Difficulty>,
+    mut scheduler: ResMut<SpawnScheduler>,
+    mut rings: ResMut<ScoringRings>,
+    mut hit_test: ResMut<HitTest>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    if let Some(playback) = playback {
+        let stash = &playback.stash;
+
+        *mode = stash.mode;
+        *mode_settings = stash.mode_settings.clone();
+        fixed_seed.0 = stash.fixed_seed;
+        difficulty.0 = stash.difficulty.clone();
+        *scheduler = stash.scheduler.clone();
+        *rings = stash.rings.clone();
+        hit_test.set_settings(stash.hit_test.clone());
+
+        commands.remove_resource::<Playback>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::default_rings;
+
+    fn replay(frames: Vec<ReplayFrame>) -> Replay {
+        Replay {
+            header: ReplayHeader {
+                seed: 42,
+                mode: GameMode::Classic,
+                mode_settings: ModeSettings::default(),
+                difficulty: DifficultyProfile::normal(),
+                spawn: SpawnPolicy::default(),
+                rings: default_rings(),
+                hit_test: HitTestSettings::default(),
+                date: 0,
+            },
+            frames,
+        }
+    }
+
+    #[test]
+    fn frames_survive_saving_exactly() {
+        let frames = vec![
+            ReplayFrame {
+                ticks: 2,
+                aim: Vec2::new(-123.456_78, 0.1 + 0.2),
+                clicked: true,
+                held: true,
+            },
+            ReplayFrame {
+                ticks: 0,
+                aim: Vec2::new(f32::MIN_POSITIVE, 959.999_9),
+                clicked: false,
+                held: true,
+            },
+            ReplayFrame {
+                ticks: u16::MAX,
+                aim: Vec2::ZERO,
+                clicked: false,
+                held: false,
+            },
+        ];
+
+        let decoded = Replay::decode(&replay(frames.clone()).encode()).unwrap();
+
+        assert_eq!(decoded.header.seed, 42);
+        assert_eq!(decoded.frames, frames);
+    }
+
+    #[test]
+    fn runs_ending_in_the_same_second_get_their_own_file() {
+        let dir = std::env::temp_dir().join(format!("replay-names-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let header = replay(Vec::new()).header;
+
+        let first = replay_path(&dir, &header, GameMode::Classic);
+        fs::write(&first, b"").unwrap();
+        let second = replay_path(&dir, &header, GameMode::Classic);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut bytes = replay(Vec::new()).encode();
+        bytes[4] = FORMAT_VERSION - 1;
+
+        assert!(matches!(
+            Replay::decode(&bytes),
+            Err(ReplayError::Invalid(_))
+        ));
+    }
+}
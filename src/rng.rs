@@ -0,0 +1,26 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// Randomness for the targets. It is reseeded at the start of every run, so playing with the same
+// seed gives the same targets in the same places.
+pub struct GameRng {
+    pub seed: u64,
+    pub rng: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+// Seed from the command line or the config, every run uses it instead of a random one
+pub struct FixedSeed(pub Option<u64>);
+
+impl FixedSeed {
+    pub fn next_seed(&self) -> u64 {
+        self.0.unwrap_or_else(|| rand::thread_rng().gen())
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Ring {
+    // Fraction of the target radius, 1.0 is the edge of the target
+    pub radius: f32,
+    pub points: u32,
+}
+
+// A ring missing a field covers the whole target for one point
+impl Default for Ring {
+    fn default() -> Self {
+        Ring {
+            radius: 1.0,
+            points: 1,
+        }
+    }
+}
+
+pub fn default_rings() -> Vec<Ring> {
+    vec![
+        Ring {
+            radius: 0.25,
+            points: 3,
+        },
+        Ring {
+            radius: 0.6,
+            points: 2,
+        },
+        Ring {
+            radius: 1.0,
+            points: 1,
+        },
+    ]
+}
+
+// Bullseye rings of a target, innermost first
+#[derive(Clone)]
+pub struct ScoringRings(Vec<Ring>);
+
+impl ScoringRings {
+    pub fn new(mut rings: Vec<Ring>) -> Self {
+        rings.sort_by(|a, b| a.radius.partial_cmp(&b.radius).unwrap());
+
+        ScoringRings(rings)
+    }
+
+    pub fn rings(&self) -> &[Ring] {
+        &self.0
+    }
+
+    // `distance` is the hit's distance from the center as a fraction of the target radius
+    pub fn points(&self, distance: f32) -> u32 {
+        self.0
+            .iter()
+            .find(|ring| distance <= ring.radius)
+            .map_or(0, |ring| ring.points)
+    }
+}
+
+// 1.0 for a hit dead center, 0.0 for one on the edge
+pub fn precision(distance: f32) -> f32 {
+    (1.0 - distance).max(0.0)
+}
@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+
+use crate::{
+    config::{save_config, Config, CrosshairStyle, WindowModeSetting},
+    daily::DailyRun,
+    difficulty::{Difficulty, DifficultyLevel},
+    paths::Paths,
+    spawner::SpawnScheduler,
+    ui::{spawn_label, spawn_sized_text_button, spawn_text_button},
+    GameState, Materials,
+};
+
+const RESOLUTIONS: [(f32, f32); 5] = [
+    (1280.0, 720.0),
+    (1366.0, 768.0),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+    (2560.0, 1440.0),
+];
+const CROSSHAIR_SIZES: [f32; 5] = [20.0, 30.0, 40.0, 60.0, 80.0];
+const CROSSHAIR_COLORS: [(&str, [f32; 3]); 5] = [
+    ("White", [1.0, 1.0, 1.0]),
+    ("Red", [1.0, 0.2, 0.2]),
+    ("Green", [0.2, 1.0, 0.2]),
+    ("Cyan", [0.2, 1.0, 1.0]),
+    ("Yellow", [1.0, 1.0, 0.2]),
+];
+// Volume goes up in steps of 10% and wraps around to mute
+const VOLUME_STEP: f32 = 0.1;
+
+struct SettingsScreenRelated;
+
+#[derive(Clone, Copy)]
+enum SettingsButton {
+    WindowMode,
+    Resolution,
+    Difficulty,
+    Volume,
+    CrosshairStyle,
+    CrosshairSize,
+    CrosshairColor,
+    Back,
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Settings).with_system(settings_setup.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Settings).with_system(settings_buttons.system()),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Settings).with_system(settings_exit.system()),
+        );
+    }
+}
+
+// The next value after `current`, wrapping around. Values that aren't in the list start over.
+fn cycle<T: Copy + PartialEq>(options: &[T], current: T) -> T {
+    let next = options
+        .iter()
+        .position(|option| *option == current)
+        .map_or(0, |index| (index + 1) % options.len());
+
+    options[next]
+}
+
+impl SettingsButton {
+    fn label(&self, config: &Config, daily: &DailyRun) -> String {
+        match self {
+            SettingsButton::WindowMode => format!("Window: {}", config.window.mode.name()),
+            SettingsButton::Resolution => format!(
+                "Resolution: {}x{}",
+                config.window.width, config.window.height
+            ),
+            SettingsButton::Difficulty if daily.is_active() => {
+                "Difficulty: set by the daily".to_string()
+            }
+            SettingsButton::Difficulty => {
+                format!("Difficulty: {}", config.difficulty.level.name())
+            }
+            SettingsButton::Volume => format!("Volume: {:.0}%", config.audio.volume * 100.0),
+            SettingsButton::CrosshairStyle => {
+                format!("Crosshair: {}", config.crosshair.style.name())
+            }
+            SettingsButton::CrosshairSize => format!("Size: {}", config.crosshair.size),
+            SettingsButton::CrosshairColor => {
+                let name = CROSSHAIR_COLORS
+                    .iter()
+                    .find(|(_, color)| *color == config.crosshair.color)
+                    .map_or("Custom", |(name, _)| *name);
+
+                format!("Color: {}", name)
+            }
+            SettingsButton::Back => "Back".to_string(),
+        }
+    }
+}
+
+fn settings_setup(
+    mut commands: Commands,
+    mut color_material: ResMut<Assets<ColorMaterial>>,
+    materials: Res<Materials>,
+    config: Res<Config>,
+    daily: Res<DailyRun>,
+) {
+    let column_style = Style {
+        flex_direction: FlexDirection::ColumnReverse,
+        align_items: AlignItems::Center,
+
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+
+                ..Default::default()
+            },
+            material: color_material.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(SettingsScreenRelated)
+        .with_children(|parent| {
+            spawn_label(parent, &materials, "Settings", 100.0);
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    material: color_material.add(Color::NONE.into()),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    let columns: [&[SettingsButton]; 2] = [
+                        &[
+                            SettingsButton::WindowMode,
+                            SettingsButton::Resolution,
+                            SettingsButton::Difficulty,
+                            SettingsButton::Volume,
+                        ],
+                        &[
+                            SettingsButton::CrosshairStyle,
+                            SettingsButton::CrosshairSize,
+                            SettingsButton::CrosshairColor,
+                        ],
+                    ];
+
+                    for buttons in columns.iter() {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: column_style.clone(),
+                                material: color_material.add(Color::NONE.into()),
+                                ..Default::default()
+                            })
+                            .with_children(|parent| {
+                                for button in buttons.iter() {
+                                    let label = button.label(&config, &daily);
+                                    spawn_sized_text_button(parent, &materials, &label, 480.0, 30.0)
+                                        .insert(*button);
+                                }
+                            });
+                    }
+                });
+
+            spawn_text_button(parent, &materials, "Back").insert(SettingsButton::Back);
+        });
+}
+
+fn settings_exit(mut commands: Commands, query: Query<Entity, With<SettingsScreenRelated>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn settings_buttons(
+    mut game_state: ResMut<State<GameState>>,
+    mut config: ResMut<Config>,
+    paths: Res<Paths>,
+    mut windows: ResMut<Windows>,
+    mut difficulty: ResMut<Difficulty>,
+    mut scheduler: ResMut<SpawnScheduler>,
+    daily: Res<DailyRun>,
+    interaction_query: Query<
+        (&Interaction, &SettingsButton, &Children),
+        Changed<Interaction>,
+    >,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, button, children) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        let window = windows.get_primary_mut().unwrap();
+
+        match button {
+            SettingsButton::WindowMode => {
+                config.window.mode = cycle(&WindowModeSetting::ALL, config.window.mode);
+                window.set_mode(config.window.mode.into());
+            }
+            SettingsButton::Resolution => {
+                let (width, height) =
+                    cycle(&RESOLUTIONS, (config.window.width, config.window.height));
+                config.window.width = width;
+                config.window.height = height;
+                window.set_resolution(width, height);
+            }
+            // The daily challenge is played on the same difficulty by everyone
+            SettingsButton::Difficulty if daily.is_active() => continue,
+            SettingsButton::Difficulty => {
+                config.difficulty.level =
+                    cycle(&DifficultyLevel::ALL, config.difficulty.level);
+
+                // Takes effect from the next run on
+                difficulty.0 = config.difficulty.profile();
+                *scheduler = SpawnScheduler::new(config.spawn.clone(), difficulty.0.spawn_rate);
+            }
+            SettingsButton::Volume => {
+                let steps = (config.audio.volume / VOLUME_STEP).round() as u32;
+                config.audio.volume = ((steps + 1) % 11) as f32 * VOLUME_STEP;
+            }
+            SettingsButton::CrosshairStyle => {
+                config.crosshair.style = cycle(&CrosshairStyle::ALL, config.crosshair.style);
+            }
+            SettingsButton::CrosshairSize => {
+                config.crosshair.size = cycle(&CROSSHAIR_SIZES, config.crosshair.size);
+            }
+            SettingsButton::CrosshairColor => {
+                let colors: Vec<[f32; 3]> =
+                    CROSSHAIR_COLORS.iter().map(|(_, color)| *color).collect();
+                config.crosshair.color = cycle(&colors, config.crosshair.color);
+            }
+            SettingsButton::Back => {
+                let _ = game_state.pop();
+                continue;
+            }
+        }
+
+        save_config(&config, &paths.config_file);
+
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value = button.label(&config, &daily);
+            }
+        }
+    }
+}
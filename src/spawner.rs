@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum SpawnPolicy {
+    // Keep this many targets on screen, every hit is replaced right away
+    KeepAlive {
+        #[serde(default = "default_count")]
+        count: u32,
+    },
+    // Spawn a target every `interval` seconds while less than `max` are alive
+    Timer {
+        #[serde(default = "default_interval")]
+        interval: f32,
+        #[serde(default = "default_max")]
+        max: u32,
+    },
+    // Spawn `count` targets at once every `interval` seconds
+    Burst {
+        #[serde(default = "default_burst_count")]
+        count: u32,
+        #[serde(default = "default_interval")]
+        interval: f32,
+        #[serde(default = "default_max")]
+        max: u32,
+    },
+}
+
+// Used for fields left out of the config
+fn default_count() -> u32 {
+    1
+}
+
+fn default_burst_count() -> u32 {
+    3
+}
+
+fn default_interval() -> f32 {
+    1.0
+}
+
+fn default_max() -> u32 {
+    5
+}
+
+impl SpawnPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpawnPolicy::KeepAlive { .. } => "keep_alive",
+            SpawnPolicy::Timer { .. } => "timer",
+            SpawnPolicy::Burst { .. } => "burst",
+        }
+    }
+}
+
+impl Default for SpawnPolicy {
+    fn default() -> Self {
+        SpawnPolicy::KeepAlive { count: 1 }
+    }
+}
+
+#[derive(Clone)]
+pub struct SpawnScheduler {
+    policy: SpawnPolicy,
+    timer: Timer,
+    started: bool,
+}
+
+impl SpawnScheduler {
+    // `rate` speeds up timed and burst spawns, 2.0 spawns twice as often
+    pub fn new(policy: SpawnPolicy, rate: f32) -> Self {
+        let interval = match policy {
+            SpawnPolicy::KeepAlive { .. } => 1.0,
+            SpawnPolicy::Timer { interval, .. } | SpawnPolicy::Burst { interval, .. } => {
+                (interval / rate.max(0.01)).max(0.05)
+            }
+        };
+
+        SpawnScheduler {
+            policy,
+            timer: Timer::from_seconds(interval, true),
+            started: false,
+        }
+    }
+
+    pub fn policy(&self) -> &SpawnPolicy {
+        &self.policy
+    }
+
+    pub fn reset(&mut self) {
+        self.timer.reset();
+        self.started = false;
+    }
+
+    // How many targets should be spawned now. The first call after a reset always spawns.
+    pub fn due(&mut self, delta: Duration, alive: u32) -> u32 {
+        let fired = if self.started {
+            match self.policy {
+                SpawnPolicy::KeepAlive { .. } => 0,
+                _ => self.timer.tick(delta).times_finished(),
+            }
+        } else {
+            self.started = true;
+            1
+        };
+
+        match self.policy {
+            SpawnPolicy::KeepAlive { count } => count.saturating_sub(alive),
+            SpawnPolicy::Timer { max, .. } => fired.min(max.saturating_sub(alive)),
+            SpawnPolicy::Burst { count, max, .. } => (fired * count).min(max.saturating_sub(alive)),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+// A click during a run, hit or miss
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Click {
+    // Crosshair position in world coordinates
+    pub position: [f32; 2],
+    // Offset from the center of the hit target, or the closest one on a miss, in target radii.
+    // None when there was no target on screen.
+    pub offset: Option<[f32; 2]>,
+    pub hit: bool,
+}
+
+// Statistics of the run that is currently being played
+#[derive(Default)]
+pub struct SessionStats {
+    pub hits: u32,
+    pub misses: u32,
+    // Seconds from a target spawning until it was hit
+    pub reaction_times: Vec<f32>,
+    // Precision of every hit, 1.0 is dead center
+    pub precisions: Vec<f32>,
+    // Seconds the mouse button was held in tracking mode, and how much of that was on target
+    pub held_time: f32,
+    pub tracked_time: f32,
+    pub clicks: Vec<Click>,
+}
+
+impl SessionStats {
+    pub fn accuracy(&self) -> f32 {
+        let shots = self.hits + self.misses;
+        if shots == 0 {
+            0.0
+        } else {
+            self.hits as f32 / shots as f32
+        }
+    }
+
+    // Fraction of the held time spent on the target in tracking mode
+    pub fn tracking(&self) -> Option<f32> {
+        if self.held_time > 0.0 {
+            Some(self.tracked_time / self.held_time)
+        } else {
+            None
+        }
+    }
+
+    pub fn mean_reaction(&self) -> Option<f32> {
+        mean(&self.reaction_times)
+    }
+
+    pub fn mean_precision(&self) -> Option<f32> {
+        mean(&self.precisions)
+    }
+
+    pub fn median_reaction(&self) -> Option<f32> {
+        if self.reaction_times.is_empty() {
+            return None;
+        }
+
+        let mut sorted = self.reaction_times.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let middle = sorted.len() / 2;
+
+        if sorted.len() % 2 == 0 {
+            Some((sorted[middle - 1] + sorted[middle]) / 2.0)
+        } else {
+            Some(sorted[middle])
+        }
+    }
+
+    pub fn best_reaction(&self) -> Option<f32> {
+        self.reaction_times.iter().cloned().fold(None, |best, time| match best {
+            Some(best) if best <= time => Some(best),
+            _ => Some(time),
+        })
+    }
+}
+
+fn mean(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f32>() / values.len() as f32)
+    }
+}
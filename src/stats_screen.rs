@@ -0,0 +1,420 @@
+use bevy::{prelude::*, window::WindowResized};
+
+use crate::{
+    highscore::{HighScores, RunResult},
+    history::History,
+    modes::GameMode,
+    playfield::{visible_size, PLAYFIELD_HEIGHT, PLAYFIELD_WIDTH},
+    ui::{spawn_label, spawn_sized_text_button, spawn_text_button},
+    GameState, Materials,
+};
+
+// How many of the most recent runs the charts show
+const CHART_RUNS: usize = 30;
+const MARGIN: f32 = 40.0;
+// Room above every chart for its title
+const TITLE_HEIGHT: f32 = 50.0;
+const LINE_WIDTH: f32 = 3.0;
+const POINT_SIZE: f32 = 8.0;
+
+struct StatsScreenRelated;
+// Parts of the charts, redrawn when another mode is picked
+struct StatsChart;
+
+enum StatsButton {
+    Mode,
+    Back,
+}
+
+// Mode the charts are drawn for
+struct StatsMode(GameMode);
+
+struct ChartMaterials {
+    background: Handle<ColorMaterial>,
+    line: Handle<ColorMaterial>,
+    point: Handle<ColorMaterial>,
+}
+
+pub struct StatsScreenPlugin;
+
+impl Plugin for StatsScreenPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(StatsMode(GameMode::default()))
+            .add_startup_system(chart_materials_setup.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Stats)
+                    .with_system(stats_setup.system().label("stats_setup"))
+                    .with_system(spawn_charts.system().after("stats_setup")),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Stats)
+                    .with_system(stats_buttons.system())
+                    .with_system(redraw_charts_on_resize.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Stats).with_system(stats_exit.system()),
+            );
+    }
+}
+
+fn chart_materials_setup(
+    mut commands: Commands,
+    mut color_material: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(ChartMaterials {
+        background: color_material.add(Color::rgb(0.85, 0.85, 0.85).into()),
+        line: color_material.add(Color::rgb(0.2, 0.45, 0.8).into()),
+        point: color_material.add(Color::rgb(0.1, 0.25, 0.5).into()),
+    });
+}
+
+fn mode_label(mode: GameMode) -> String {
+    format!("Mode: {}", mode.name())
+}
+
+// Accuracy in percent, tracking runs count the time on target instead
+fn accuracy(run: &RunResult) -> Option<f32> {
+    if run.mode == GameMode::Tracking {
+        run.tracking.map(|tracking| tracking * 100.0)
+    } else if run.hits + run.misses > 0 {
+        Some(run.accuracy() * 100.0)
+    } else {
+        None
+    }
+}
+
+// Mean reaction time in milliseconds
+fn reaction(run: &RunResult) -> Option<f32> {
+    run.reaction_mean.map(|mean| mean * 1000.0)
+}
+
+fn personal_bests(mode: GameMode, history: &History, high_scores: &HighScores) -> String {
+    let runs: Vec<&RunResult> = history.of_mode(mode).map(|entry| &entry.run).collect();
+    // The high scores go back further than the history
+    let score = runs
+        .iter()
+        .map(|run| run.score)
+        .chain(high_scores.best(mode).map(|run| run.score))
+        .max();
+
+    let score = match score {
+        Some(score) => score,
+        None => return format!("{}: no runs yet", mode.name()),
+    };
+
+    let mut line = format!("{}: {} points", mode.name(), score);
+    let best_accuracy = runs.iter().filter_map(|run| accuracy(run)).fold(None, max);
+    if let Some(best) = best_accuracy {
+        let name = if mode == GameMode::Tracking {
+            "on target"
+        } else {
+            "accuracy"
+        };
+        line.push_str(&format!(", {:.0}% {}", best, name));
+    }
+    let best_reaction = runs.iter().filter_map(|run| reaction(run)).fold(None, min);
+    if let Some(best) = best_reaction {
+        line.push_str(&format!(", {:.0} ms reaction", best));
+    }
+
+    line
+}
+
+fn max(best: Option<f32>, value: f32) -> Option<f32> {
+    Some(best.map_or(value, |best| best.max(value)))
+}
+
+fn min(best: Option<f32>, value: f32) -> Option<f32> {
+    Some(best.map_or(value, |best| best.min(value)))
+}
+
+fn stats_setup(
+    mut commands: Commands,
+    mut color_material: ResMut<Assets<ColorMaterial>>,
+    materials: Res<Materials>,
+    history: Res<History>,
+    high_scores: Res<HighScores>,
+    mode: Res<GameMode>,
+    mut stats_mode: ResMut<StatsMode>,
+) {
+    // Starts on the mode picked on the main menu
+    stats_mode.0 = *mode;
+
+    // The charts are drawn as sprites on the right, the UI keeps to the left
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(40.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+
+                ..Default::default()
+            },
+            material: color_material.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(StatsScreenRelated)
+        .with_children(|parent| {
+            spawn_label(parent, &materials, "Statistics", 80.0);
+            spawn_sized_text_button(parent, &materials, &mode_label(stats_mode.0), 400.0, 30.0)
+                .insert(StatsButton::Mode);
+
+            spawn_label(parent, &materials, "Personal bests", 40.0);
+            for mode in GameMode::ALL.iter() {
+                let bests = personal_bests(*mode, &history, &high_scores);
+                spawn_label(parent, &materials, &bests, 25.0);
+            }
+
+            spawn_text_button(parent, &materials, "Back").insert(StatsButton::Back);
+        });
+}
+
+fn spawn_charts(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    materials: Res<Materials>,
+    chart_materials: Res<ChartMaterials>,
+    history: Res<History>,
+    stats_mode: Res<StatsMode>,
+) {
+    draw_charts(
+        &mut commands,
+        windows.get_primary().unwrap(),
+        &materials,
+        &chart_materials,
+        &history,
+        stats_mode.0,
+    );
+}
+
+// Score, accuracy and reaction time of the last runs of `mode`, stacked on the right
+fn draw_charts(
+    commands: &mut Commands,
+    window: &Window,
+    materials: &Materials,
+    chart_materials: &ChartMaterials,
+    history: &History,
+    mode: GameMode,
+) {
+    let runs: Vec<&RunResult> = history.of_mode(mode).map(|entry| &entry.run).collect();
+    let runs = &runs[runs.len().saturating_sub(CHART_RUNS)..];
+
+    let accuracy_title = if mode == GameMode::Tracking {
+        "On target %"
+    } else {
+        "Accuracy %"
+    };
+    let charts: [(&str, Vec<Option<f32>>); 3] = [
+        ("Score", runs.iter().map(|run| Some(run.score as f32)).collect()),
+        (accuracy_title, runs.iter().map(|run| accuracy(run)).collect()),
+        ("Reaction ms", runs.iter().map(|run| reaction(run)).collect()),
+    ];
+
+    // World coordinates, the origin is the center of the playfield. The UI takes the left of the
+    // window and the bars cover everything outside the playfield.
+    let visible = visible_size(window);
+    let left = -visible.x / 2.0 + visible.x * 0.4 + MARGIN;
+    let right = PLAYFIELD_WIDTH / 2.0 - MARGIN;
+    let height = (PLAYFIELD_HEIGHT - 2.0 * MARGIN) / charts.len() as f32 - TITLE_HEIGHT;
+    let mut top = PLAYFIELD_HEIGHT / 2.0 - MARGIN;
+
+    for (title, values) in charts.iter() {
+        let area = Rect {
+            left,
+            right,
+            top: top - TITLE_HEIGHT,
+            bottom: top - TITLE_HEIGHT - height,
+        };
+        let title = chart_title(title, values, runs.len());
+
+        spawn_chart(commands, materials, chart_materials, area, &title, values);
+        top -= TITLE_HEIGHT + height;
+    }
+}
+
+fn chart_title(title: &str, values: &[Option<f32>], runs: usize) -> String {
+    match (values.iter().flatten().last(), values.iter().flatten().copied().fold(None, max)) {
+        (Some(last), Some(highest)) => format!(
+            "{}: last {:.0}, highest {:.0} over {} runs",
+            title, last, highest, runs
+        ),
+        _ => format!("{}: no runs yet", title),
+    }
+}
+
+// A line through the values from left to right, scaled from zero to the highest value.
+// Runs without a value leave a gap in the line.
+fn spawn_chart(
+    commands: &mut Commands,
+    materials: &Materials,
+    chart_materials: &ChartMaterials,
+    area: Rect<f32>,
+    title: &str,
+    values: &[Option<f32>],
+) {
+    let width = area.right - area.left;
+    let height = area.top - area.bottom;
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: chart_materials.background.clone(),
+            transform: Transform::from_xyz(area.left + width / 2.0, area.bottom + height / 2.0, 0.0),
+            sprite: Sprite::new(Vec2::new(width, height)),
+            ..Default::default()
+        })
+        .insert(StatsChart)
+        .insert(StatsScreenRelated);
+
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                title,
+                TextStyle {
+                    font: materials.font.clone(),
+                    font_size: 25.0,
+                    color: Color::rgb(0.4, 0.4, 0.4),
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Bottom,
+                    horizontal: HorizontalAlign::Left,
+                },
+            ),
+            transform: Transform::from_xyz(area.left, area.top + 10.0, 3.0),
+            ..Default::default()
+        })
+        .insert(StatsChart)
+        .insert(StatsScreenRelated);
+
+    let highest = values.iter().flatten().copied().fold(None, max).unwrap_or(0.0);
+    let scale = if highest > 0.0 { height / highest } else { 0.0 };
+    let step = if values.len() > 1 {
+        width / (values.len() - 1) as f32
+    } else {
+        0.0
+    };
+    // A single run is drawn in the middle
+    let start = if values.len() > 1 { area.left } else { area.left + width / 2.0 };
+
+    let points: Vec<Option<Vec2>> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            value.map(|value| Vec2::new(start + index as f32 * step, area.bottom + value * scale))
+        })
+        .collect();
+
+    for pair in points.windows(2) {
+        if let (Some(from), Some(to)) = (pair[0], pair[1]) {
+            let delta = to - from;
+            let center = (from + to) / 2.0;
+
+            commands
+                .spawn_bundle(SpriteBundle {
+                    material: chart_materials.line.clone(),
+                    transform: Transform {
+                        translation: center.extend(1.0),
+                        rotation: Quat::from_rotation_z(delta.y.atan2(delta.x)),
+                        ..Default::default()
+                    },
+                    sprite: Sprite::new(Vec2::new(delta.length(), LINE_WIDTH)),
+                    ..Default::default()
+                })
+                .insert(StatsChart)
+                .insert(StatsScreenRelated);
+        }
+    }
+
+    for point in points.iter().flatten() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                material: chart_materials.point.clone(),
+                transform: Transform::from_translation(point.extend(2.0)),
+                sprite: Sprite::new(Vec2::new(POINT_SIZE, POINT_SIZE)),
+                ..Default::default()
+            })
+            .insert(StatsChart)
+            .insert(StatsScreenRelated);
+    }
+}
+
+// The charts are fitted between the UI and the edge of the playfield, which move with the window
+fn redraw_charts_on_resize(
+    mut commands: Commands,
+    mut resized: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    materials: Res<Materials>,
+    chart_materials: Res<ChartMaterials>,
+    history: Res<History>,
+    stats_mode: Res<StatsMode>,
+    chart_query: Query<Entity, With<StatsChart>>,
+) {
+    let window = windows.get_primary().unwrap();
+    if !resized.iter().any(|event| event.id == window.id()) {
+        return;
+    }
+
+    for entity in chart_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    draw_charts(
+        &mut commands,
+        window,
+        &materials,
+        &chart_materials,
+        &history,
+        stats_mode.0,
+    );
+}
+
+fn stats_exit(mut commands: Commands, query: Query<Entity, With<StatsScreenRelated>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn stats_buttons(
+    mut commands: Commands,
+    mut game_state: ResMut<State<GameState>>,
+    mut stats_mode: ResMut<StatsMode>,
+    windows: Res<Windows>,
+    materials: Res<Materials>,
+    chart_materials: Res<ChartMaterials>,
+    history: Res<History>,
+    interaction_query: Query<(&Interaction, &StatsButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+    chart_query: Query<Entity, With<StatsChart>>,
+) {
+    for (interaction, button, children) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            StatsButton::Mode => {
+                stats_mode.0 = stats_mode.0.next();
+
+                for child in children.iter() {
+                    if let Ok(mut text) = text_query.get_mut(*child) {
+                        text.sections[0].value = mode_label(stats_mode.0);
+                    }
+                }
+
+                for entity in chart_query.iter() {
+                    commands.entity(entity).despawn();
+                }
+                draw_charts(
+                    &mut commands,
+                    windows.get_primary().unwrap(),
+                    &materials,
+                    &chart_materials,
+                    &history,
+                    stats_mode.0,
+                );
+            }
+            StatsButton::Back => {
+                let _ = game_state.pop();
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+pub enum LoadError<E> {
+    // Neither the file nor its backup exist
+    Missing,
+    Io(io::Error),
+    // The file could be read but `parse` rejected it, and there was no usable backup
+    Invalid(E),
+}
+
+// `config.json` -> `config.json.<suffix>` in the same directory
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map_or_else(OsString::new, |name| name.to_os_string());
+    name.push(".");
+    name.push(suffix);
+
+    path.with_file_name(name)
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling(path, "bak")
+}
+
+// Replaces `path` with `contents` so that a crash leaves either the old or the new file behind,
+// never a truncated one. The file that is replaced is kept as the backup.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+
+    let tmp = sibling(path, "tmp");
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+    }
+
+    if path.is_file() {
+        fs::rename(path, backup_path(path))?;
+    }
+    fs::rename(&tmp, path)?;
+
+    sync_dir(dir);
+
+    Ok(())
+}
+
+// Makes the renames durable, only possible on unix
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+// Moves a file that can't be loaded out of the way so it can be fixed by hand
+pub fn set_aside(path: &Path) -> io::Result<PathBuf> {
+    let broken = sibling(path, "broken");
+    fs::rename(path, &broken)?;
+
+    Ok(broken)
+}
+
+// Reads and parses `path`. When it is missing or broken the backup is used instead and restored,
+// a broken file is set aside first.
+pub fn load<T, E>(path: &Path, parse: impl Fn(&str) -> Result<T, E>) -> Result<T, LoadError<E>> {
+    let err = match fs::read_to_string(path) {
+        Ok(contents) => match parse(contents.as_str()) {
+            Ok(value) => return Ok(value),
+            Err(err) => LoadError::Invalid(err),
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => LoadError::Missing,
+        Err(err) => LoadError::Io(err),
+    };
+
+    let backup = backup_path(path);
+    let contents = match fs::read_to_string(&backup) {
+        Ok(contents) => contents,
+        Err(_) => return Err(err),
+    };
+    let value = match parse(contents.as_str()) {
+        Ok(value) => value,
+        Err(_) => return Err(err),
+    };
+
+    eprintln!(
+        "Could not load {}, restoring it from {}",
+        path.display(),
+        backup.display()
+    );
+
+    // A file that exists but can't be read is left alone
+    let restore = match err {
+        LoadError::Missing => true,
+        LoadError::Invalid(_) => match set_aside(path) {
+            Ok(_) => true,
+            Err(err) => {
+                eprintln!("Could not move {}: {}", path.display(), err);
+                false
+            }
+        },
+        LoadError::Io(_) => false,
+    };
+    if restore {
+        if let Err(err) = write_atomic(path, contents.as_str()) {
+            eprintln!("Could not restore {}: {}", path.display(), err);
+        }
+    }
+
+    Ok(value)
+}
+
+// Loads save data written by `save_json`. Anything that goes wrong gives the default value, a
+// broken file is set aside so the next save doesn't overwrite it.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match load(path, |contents| serde_json::from_str(contents)) {
+        Ok(value) => value,
+        Err(LoadError::Missing) => T::default(),
+        Err(LoadError::Io(err)) => {
+            eprintln!("Could not read {}: {}", path.display(), err);
+            T::default()
+        }
+        Err(LoadError::Invalid(err)) => {
+            eprintln!("Could not parse {}: {}", path.display(), err);
+            if let Err(err) = set_aside(path) {
+                eprintln!("Could not move {}: {}", path.display(), err);
+            }
+            T::default()
+        }
+    }
+}
+
+pub fn save_json<T: Serialize>(path: &Path, value: &T) {
+    let j = serde_json::to_string_pretty(value).unwrap();
+
+    if let Err(err) = write_atomic(path, &j) {
+        eprintln!("Could not write {}: {}", path.display(), err);
+    }
+}
+
+// Adds a line to the end of `path`, creating it if needed. Nothing already in the file is ever
+// rewritten, a crash can only cut the new line short.
+pub fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+
+    // Starts on a new line after a line that was cut short, so only that one is lost
+    let mut start = "";
+    if file.metadata()?.len() > 0 {
+        let mut last = [0];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            start = "\n";
+        }
+    }
+
+    file.write_all(format!("{}{}\n", start, line).as_bytes())?;
+    file.sync_data()
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    cursor::CursorWorld,
+    difficulty::Difficulty,
+    hit_test::HitTest,
+    ingame::{target_bundle, target_width, ScoreEvent, Shot},
+    modes::GameMode,
+    physics::{SimClock, TICK},
+    playfield::{playfield_size, PLAYFIELD_HEIGHT},
+    rng::GameRng,
+    stats::SessionStats,
+    GameState, Materials, Score, SpawnedAt, Target,
+};
+
+// Points for every second the crosshair is held on the target
+const POINTS_PER_SECOND: f32 = 10.0;
+// Distance a target moves each second, relative to the playfield height
+const WANDER_SPEED: f32 = 0.35;
+// How quickly a target turns towards a new heading
+const STEERING: f32 = 2.5;
+
+// A target that drifts along a smooth random path
+struct Wander {
+    velocity: Vec2,
+    heading: Vec2,
+    // Seconds until a new heading is picked
+    turn_in: f32,
+}
+
+pub struct TrackingPlugin;
+
+impl Plugin for TrackingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(tracking(GameState::InGame))
+            .add_system_set(tracking(GameState::Replay));
+    }
+}
+
+fn tracking(state: GameState) -> SystemSet {
+    SystemSet::on_update(state)
+        .with_system(
+            spawn_tracking_target
+                .system()
+                .label("spawn")
+                .after("sim_clock"),
+        )
+        .with_system(wander_movement.system().label("movement").after("spawn"))
+        .with_system(
+            track_target
+                .system()
+                .label("click")
+                .after("movement")
+                .after("cursor_world"),
+        )
+}
+
+fn random_heading(rng: &mut impl Rng) -> Vec2 {
+    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+fn spawn_tracking_target(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    clock: Res<SimClock>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    query: Query<Entity, With<Target>>,
+    mut rng: ResMut<GameRng>,
+) {
+    if *mode != GameMode::Tracking || query.iter().next().is_some() {
+        return;
+    }
+
+    let rng = &mut rng.rng;
+    let width = target_width(&difficulty.0);
+    let bounds = playfield_size() / 2.0 - Vec2::new(width, width) / 2.0;
+    let x = rng.gen_range(-bounds.x..bounds.x);
+    let y = rng.gen_range(-bounds.y..bounds.y);
+
+    commands
+        .spawn_bundle(target_bundle(&materials, x, y, width))
+        .insert(Target)
+        .insert(SpawnedAt(clock.elapsed()))
+        .insert(Wander {
+            velocity: Vec2::ZERO,
+            heading: random_heading(rng),
+            turn_in: rng.gen_range(0.5..1.5),
+        });
+}
+
+fn wander_movement(
+    clock: Res<SimClock>,
+    mut query: Query<(&mut Transform, &Sprite, &mut Wander)>,
+    mut rng: ResMut<GameRng>,
+) {
+    let speed = PLAYFIELD_HEIGHT * WANDER_SPEED;
+    let rng = &mut rng.rng;
+
+    for (mut tf, sprite, mut wander) in query.iter_mut() {
+        let bounds = playfield_size() / 2.0 - sprite.size / 2.0;
+
+        for _ in 0..clock.pending() {
+            wander.turn_in -= TICK;
+            if wander.turn_in <= 0.0 {
+                wander.heading = random_heading(rng);
+                wander.turn_in = rng.gen_range(0.5..1.5);
+            }
+
+            let desired = wander.heading * speed;
+            wander.velocity = wander.velocity.lerp(desired, STEERING * TICK);
+            tf.translation += wander.velocity.extend(0.0) * TICK;
+
+            // Bounce off the edges of the playfield
+            if tf.translation.x.abs() > bounds.x {
+                tf.translation.x = tf.translation.x.signum() * bounds.x;
+                wander.velocity.x = -wander.velocity.x;
+                wander.heading.x = -wander.heading.x;
+            }
+            if tf.translation.y.abs() > bounds.y {
+                tf.translation.y = tf.translation.y.signum() * bounds.y;
+                wander.velocity.y = -wander.velocity.y;
+                wander.heading.y = -wander.heading.y;
+            }
+        }
+    }
+}
+
+fn track_target(
+    shot: Res<Shot>,
+    cursor_world: Res<CursorWorld>,
+    clock: Res<SimClock>,
+    mode: Res<GameMode>,
+    query: Query<(&Transform, &Sprite), With<Wander>>,
+    hit_test: Res<HitTest>,
+    mut stats: ResMut<SessionStats>,
+    mut score: ResMut<Score>,
+    mut ev_score: EventWriter<ScoreEvent>,
+) {
+    if *mode != GameMode::Tracking || !shot.pressed {
+        return;
+    }
+
+    let on_target = cursor_world.0.map_or(false, |position| {
+        query.iter().any(|(tf, sprite)| {
+            let offset = position - Vec2::from(tf.translation);
+            hit_test.hits(offset, sprite.size)
+        })
+    });
+
+    let step = clock.pending() as f32 * TICK;
+    stats.held_time += step;
+    if on_target {
+        stats.tracked_time += step;
+    }
+
+    let points = (stats.tracked_time * POINTS_PER_SECOND) as u32;
+    if points != score.0 {
+        score.0 = points;
+        ev_score.send(ScoreEvent(score.0));
+    }
+}
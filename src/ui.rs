@@ -0,0 +1,75 @@
+use bevy::{ecs::system::EntityCommands, prelude::*};
+
+use crate::Materials;
+
+pub fn spawn_label<'a, 'b>(
+    parent: &'b mut ChildBuilder<'a, '_>,
+    materials: &Materials,
+    value: &str,
+    font_size: f32,
+) -> EntityCommands<'a, 'b> {
+    parent.spawn_bundle(TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(10.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: materials.font.clone(),
+                font_size,
+                color: Color::rgb(0.4, 0.4, 0.4),
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    })
+}
+
+// A flat button with a text label as its only child
+pub fn spawn_text_button<'a, 'b>(
+    parent: &'b mut ChildBuilder<'a, '_>,
+    materials: &Materials,
+    label: &str,
+) -> EntityCommands<'a, 'b> {
+    spawn_sized_text_button(parent, materials, label, 320.0, 40.0)
+}
+
+// Same as `spawn_text_button` with room for longer labels
+pub fn spawn_sized_text_button<'a, 'b>(
+    parent: &'b mut ChildBuilder<'a, '_>,
+    materials: &Materials,
+    label: &str,
+    width: f32,
+    font_size: f32,
+) -> EntityCommands<'a, 'b> {
+    let mut button = parent.spawn_bundle(ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(width), Val::Px(70.0)),
+            margin: Rect::all(Val::Px(10.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+
+            ..Default::default()
+        },
+        material: materials.menu_button.clone(),
+        ..Default::default()
+    });
+
+    button.with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                label,
+                TextStyle {
+                    font: materials.font.clone(),
+                    font_size,
+                    color: Color::rgb(0.927, 0.927, 0.927),
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        });
+    });
+
+    button
+}